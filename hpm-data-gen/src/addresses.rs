@@ -0,0 +1,216 @@
+//! cross-check peripheral base addresses against sdk_code `HPM_*_BASE` defines and the vendor SVD
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::family::Family;
+
+#[derive(Debug, serde::Deserialize)]
+struct SvdDevice {
+    peripherals: SvdPeripherals,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SvdPeripherals {
+    peripheral: Vec<SvdPeripheral>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SvdPeripheral {
+    name: String,
+    base_address: String,
+}
+
+fn parse_u32(s: &str) -> anyhow::Result<u32> {
+    let s = s.trim().trim_end_matches(['U', 'u', 'L', 'l']);
+    let value = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16)?,
+        None => s.parse()?,
+    };
+    Ok(value)
+}
+
+// <peripheral><name>UART0</name>...<baseAddress>0xF0040000</baseAddress>
+fn load_svd_addresses(path: &Path) -> anyhow::Result<BTreeMap<String, u32>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", path, e))?;
+    let device: SvdDevice = quick_xml::de::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse SVD: {:?}: {}", path, e))?;
+
+    device
+        .peripherals
+        .peripheral
+        .iter()
+        .map(|p| Ok((p.name.clone(), parse_u32(&p.base_address)?)))
+        .collect()
+}
+
+// #define HPM_UART0_BASE (0xF0040000UL)
+fn parse_sdk_addresses(content: &str) -> anyhow::Result<BTreeMap<String, u32>> {
+    let base_pattern = regex::Regex::new(r"#define\s+HPM_(\w+)_BASE\s+\(?(0x[0-9A-Fa-f]+)U?L?\)?")
+        .expect("Invalid regex");
+    base_pattern
+        .captures_iter(content)
+        .map(|cap| {
+            Ok((
+                cap.get(1).unwrap().as_str().to_string(),
+                parse_u32(cap.get(2).unwrap().as_str())?,
+            ))
+        })
+        .collect()
+}
+
+fn load_sdk_addresses(path: &Path) -> anyhow::Result<BTreeMap<String, u32>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", path, e))?;
+
+    let addresses = parse_sdk_addresses(&content)?;
+    anyhow::ensure!(!addresses.is_empty(), "No HPM_*_BASE defines in {:?}", path);
+
+    Ok(addresses)
+}
+
+/// Compare the peripherals of a chip with one address source.
+///
+/// Peripherals missing on either side are reported, returns the address differences.
+fn compare(
+    chip_name: &str,
+    source: &str,
+    peripherals: &BTreeMap<String, u32>,
+    addresses: &BTreeMap<String, u32>,
+    renames: &HashMap<String, String>,
+) -> Vec<String> {
+    let addresses: BTreeMap<&str, u32> = addresses
+        .iter()
+        .map(|(name, &address)| {
+            let name = renames.get(name).unwrap_or(name);
+            (name.as_str(), address)
+        })
+        .collect();
+
+    let missing: Vec<_> = addresses
+        .keys()
+        .filter(|name| !peripherals.contains_key(**name))
+        .collect();
+    if !missing.is_empty() {
+        println!(
+            "    {}: in {} but not in YAML: {:?}",
+            chip_name, source, missing
+        );
+    }
+
+    let extra: Vec<_> = peripherals
+        .keys()
+        .filter(|name| !addresses.contains_key(name.as_str()))
+        .collect();
+    if !extra.is_empty() {
+        println!(
+            "    {}: in YAML but not in {}: {:?}",
+            chip_name, source, extra
+        );
+    }
+
+    peripherals
+        .iter()
+        .filter_map(|(name, &address)| {
+            let &expected = addresses.get(name.as_str())?;
+            (address != expected).then(|| {
+                format!(
+                    "{} at 0x{:08X}, {} has 0x{:08X}",
+                    name, address, source, expected
+                )
+            })
+        })
+        .collect()
+}
+
+/// Peripherals missing on either side are only reported, a chip can leave out blocks of its
+/// family. An address that differs fails the chip.
+pub fn check_addresses<P: AsRef<Path>>(
+    data_dir: P,
+    family: &Family,
+    chip: &hpm_data_serde::Chip,
+) -> anyhow::Result<()> {
+    let mut peripherals = BTreeMap::new();
+    for core in &chip.cores {
+        for p in &core.peripherals {
+            peripherals.insert(p.name.clone(), p.address);
+        }
+    }
+
+    let header_path = family.header(&data_dir, &family.headers.soc);
+    let sdk_addresses = load_sdk_addresses(&header_path)?;
+    let mut differences = compare(
+        &chip.name,
+        "SDK",
+        &peripherals,
+        &sdk_addresses,
+        &family.base_renames,
+    );
+
+    if let Some(svd) = &family.svd {
+        let svd_path = data_dir.as_ref().parent().unwrap().join(svd);
+        let svd_addresses = load_svd_addresses(&svd_path)?;
+        differences.extend(compare(
+            &chip.name,
+            "SVD",
+            &peripherals,
+            &svd_addresses,
+            &family.base_renames,
+        ));
+    }
+
+    anyhow::ensure!(
+        differences.is_empty(),
+        "Peripheral addresses differ: {:?}",
+        differences
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u32_values() {
+        assert_eq!(parse_u32("0xf0040000").unwrap(), 0xF004_0000);
+        assert_eq!(parse_u32("0xF0040000UL").unwrap(), 0xF004_0000);
+        assert_eq!(parse_u32(" 0X1000U ").unwrap(), 0x1000);
+        assert_eq!(parse_u32("256").unwrap(), 256);
+        assert!(parse_u32("0x1_0000_0000").is_err());
+        assert!(parse_u32("HPM_UART0_BASE").is_err());
+    }
+
+    #[test]
+    fn sdk_base_defines() {
+        let content = r#"
+/* UART0 */
+#define HPM_UART0_BASE (0xF0040000UL)
+#define HPM_UART0 ((UART_Type *) HPM_UART0_BASE)
+
+/* DMAMUX */
+#define HPM_DMAMUX_BASE (0xF00C4000UL)
+#define HPM_DMAMUX ((DMAMUX_Type *) HPM_DMAMUX_BASE)
+"#;
+        let addresses = parse_sdk_addresses(content).unwrap();
+        assert_eq!(
+            addresses,
+            BTreeMap::from([
+                ("DMAMUX".to_string(), 0xF00C_4000),
+                ("UART0".to_string(), 0xF004_0000),
+            ])
+        );
+    }
+
+    #[test]
+    fn svd_base_addresses() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../svd/HPM5361_svd.xml");
+        let addresses = load_svd_addresses(&path).unwrap();
+        assert_eq!(addresses["UART0"], 0xF004_0000);
+        assert_eq!(addresses["DMAMUX"], 0xF00C_4000);
+        assert_eq!(addresses["PLIC"], 0xE400_0000);
+    }
+}
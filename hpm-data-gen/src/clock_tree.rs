@@ -0,0 +1,303 @@
+//! clock tree from sdk_code: hpm_sysctl_drv.h, hpm_clock_drv.h, hpm_sysctl_regs.h and hpm_pllctl(v2)_regs.h
+
+use std::path::Path;
+
+use hpm_data_serde::chip::core::clock_tree::{MaxFrequency, Mux, Node, Pll, Source};
+use hpm_data_serde::chip::core::ClockTree;
+
+use crate::family::Family;
+
+fn read_header(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", path, e))
+}
+
+// #define SYSCTL_CLOCK_DIV_MASK (0xFFU) => 8
+fn mask_width(content: &str, name_pattern: &str) -> Option<u8> {
+    let pattern = regex::Regex::new(&format!(
+        r"#define\s+{}\s+\(0x([0-9A-Fa-f]+)U?L?\)",
+        name_pattern
+    ))
+    .expect("Invalid regex");
+    let cap = pattern.captures(content)?;
+    let mask = u32::from_str_radix(cap.get(1).unwrap().as_str(), 16).ok()?;
+    Some(mask.count_ones() as u8)
+}
+
+// typedef enum { clock_source_osc0_clk0 = 0, clock_source_pll0_clk0 = 1, ... } clock_source_t;
+fn parse_clock_sources(content: &str) -> anyhow::Result<Vec<Source>> {
+    let enum_pattern =
+        regex::Regex::new(r"typedef\s+enum\s*\w*\s*\{([^}]*)\}\s*clock_source_t\s*;")
+            .expect("Invalid regex");
+    let Some(cap) = enum_pattern.captures(content) else {
+        anyhow::bail!("clock_source_t not found");
+    };
+    let body = cap.get(1).unwrap().as_str();
+
+    let comment_pattern = regex::Regex::new(r"(?s)/\*.*?\*/|//[^\n]*").expect("Invalid regex");
+    let body = comment_pattern.replace_all(body, "");
+
+    let mut sources = Vec::new();
+    let mut next = 0;
+    for item in body.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (name, value) = match item.split_once('=') {
+            Some((name, value)) => {
+                let value = value.trim().trim_end_matches(['U', 'u', 'L', 'l']);
+                (name.trim(), value.parse::<usize>()?)
+            }
+            None => (item, next),
+        };
+        next = value + 1;
+        let Some(name) = name.strip_prefix("clock_source_") else {
+            continue;
+        };
+        // clock_source_general_source_end
+        if name.ends_with("_end") {
+            continue;
+        }
+        sources.push(Source {
+            name: name.to_ascii_uppercase(),
+            index: value,
+        });
+    }
+    Ok(sources)
+}
+
+// clk_adc_src_ahb0 = MAKE_CLK_SRC(CLK_SRC_GROUP_ADC, 0) => ADC: AHB0 = 0
+fn parse_clock_muxes(content: &str) -> Vec<Mux> {
+    let pattern = regex::Regex::new(
+        r"\bclk_(\w+)\s*=\s*MAKE_CLK_SRC\(\s*CLK_SRC_GROUP_(\w+)\s*,\s*(\d+)U?\s*\)",
+    )
+    .expect("Invalid regex");
+
+    let mut muxes: Vec<Mux> = Vec::new();
+    for cap in pattern.captures_iter(content) {
+        let group = cap.get(2).unwrap().as_str();
+        // the general sources, same as clock_source_t
+        if group == "COMMON" || group == "INVALID" {
+            continue;
+        }
+        let name = cap.get(1).unwrap().as_str();
+        let prefix = format!("{}_", group.to_ascii_lowercase());
+        let name = name.strip_prefix(&prefix).unwrap_or(name);
+        let name = name.strip_prefix("src_").unwrap_or(name);
+
+        let source = Source {
+            name: name.to_ascii_uppercase(),
+            index: cap.get(3).unwrap().as_str().parse().unwrap(),
+        };
+        match muxes.iter_mut().find(|m| m.name == group) {
+            Some(mux) => mux.sources.push(source),
+            None => muxes.push(Mux {
+                name: group.to_string(),
+                sources: vec![source],
+            }),
+        }
+    }
+    for mux in &mut muxes {
+        mux.sources.sort_by_key(|s| s.index);
+        mux.sources.dedup();
+    }
+    muxes.sort_by(|a, b| a.name.cmp(&b.name));
+    muxes
+}
+
+// #define PLLCTLV2_PLL_PLL0 (0UL)
+fn parse_plls(content: &str, sources: &[Source]) -> Vec<Pll> {
+    let pattern = regex::Regex::new(r"#define\s+PLLCTL(?:V2)?_PLL_PLL(\d+)\s+\((\d+)UL\)")
+        .expect("Invalid regex");
+    let div_width = mask_width(content, r"PLLCTL\w*_DIV\w*_DIV_MASK");
+
+    let mut plls: Vec<Pll> = pattern
+        .captures_iter(content)
+        .map(|cap| {
+            let name = format!("PLL{}", cap.get(1).unwrap().as_str());
+            let prefix = format!("{}_", name);
+            Pll {
+                outputs: sources
+                    .iter()
+                    .filter(|s| s.name.starts_with(&prefix))
+                    .map(|s| s.name.clone())
+                    .collect(),
+                index: cap.get(2).unwrap().as_str().parse().unwrap(),
+                name,
+                div_width,
+            }
+        })
+        .collect();
+    plls.sort_by_key(|p| p.index);
+    plls
+}
+
+/// Must run after SYSCTL, the clock nodes are the `SYSCTL.CLOCK` entries of each core.
+pub fn add_clock_tree_from_sdk<P: AsRef<Path>>(
+    data_dir: P,
+    family: &Family,
+    chip: &mut hpm_data_serde::Chip,
+) -> anyhow::Result<()> {
+    let headers = &family.headers;
+    let sysctl_drv = read_header(&family.header(&data_dir, &headers.sysctl_drv))?;
+    let clock_drv = read_header(&family.header(&data_dir, &headers.clock_drv))?;
+    let sysctl_regs = read_header(&family.header(&data_dir, &headers.sysctl))?;
+    let pllctl_regs = read_header(&family.header(&data_dir, &headers.pllctl))?;
+
+    let sources = parse_clock_sources(&sysctl_drv)?;
+    let muxes = parse_clock_muxes(&clock_drv);
+    let plls = parse_plls(&pllctl_regs, &sources);
+
+    let Some(mux_width) = mask_width(&sysctl_regs, "SYSCTL_CLOCK_MUX_MASK") else {
+        anyhow::bail!("SYSCTL_CLOCK_MUX_MASK not found");
+    };
+    let Some(div_width) = mask_width(&sysctl_regs, "SYSCTL_CLOCK_DIV_MASK") else {
+        anyhow::bail!("SYSCTL_CLOCK_DIV_MASK not found");
+    };
+
+    println!(
+        "    Chip: {} clock tree: {} sources, {} PLLs, {} muxes",
+        chip.name,
+        sources.len(),
+        plls.len(),
+        muxes.len()
+    );
+
+    let mut max_frequencies: Vec<MaxFrequency> = family
+        .sysctl
+        .max_frequency
+        .iter()
+        .map(|(node, &frequency)| MaxFrequency {
+            node: node.clone(),
+            frequency,
+        })
+        .collect();
+    max_frequencies.sort();
+
+    for core in &mut chip.cores {
+        for name in family.sysctl.max_frequency.keys() {
+            anyhow::ensure!(
+                core.clocks.iter().any(|clk| &clk.name == name),
+                "sysctl.max_frequency: no clock node {}",
+                name
+            );
+        }
+
+        let nodes = core
+            .clocks
+            .iter()
+            .map(|clk| Node {
+                name: clk.name.clone(),
+                index: clk.index,
+                mux_width,
+                div_width,
+                peripherals: core
+                    .peripherals
+                    .iter()
+                    .filter(|p| p.sysctl.as_ref().and_then(|s| s.clock_node) == Some(clk.index))
+                    .map(|p| p.name.clone())
+                    .collect(),
+            })
+            .collect();
+        core.clock_tree = Some(ClockTree {
+            sources: sources.clone(),
+            plls: plls.clone(),
+            nodes,
+            muxes: muxes.clone(),
+            max_frequencies: max_frequencies.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_sources() {
+        let content = r#"
+/**
+ * @brief Clock source definitions
+ */
+typedef enum {
+    clock_source_osc0_clk0 = 0,
+    clock_source_pll0_clk0 = 1,
+    clock_source_pll0_clk1 = 2,
+    clock_source_pll0_clk2 = 3,
+    clock_source_pll1_clk0 = 4,
+    clock_source_pll1_clk1 = 5,
+    clock_source_pll1_clk2 = 6,
+    clock_source_pll1_clk3 = 7,
+    clock_source_general_source_end,
+} clock_source_t;
+"#;
+        let sources = parse_clock_sources(content).unwrap();
+        assert_eq!(sources.len(), 8);
+        assert_eq!(sources[0].name, "OSC0_CLK0");
+        assert_eq!(sources[7].name, "PLL1_CLK3");
+        assert_eq!(sources[7].index, 7);
+
+        assert!(parse_clock_sources("typedef enum { a = 0 } clock_name_t;").is_err());
+    }
+
+    #[test]
+    fn clock_muxes() {
+        let content = r#"
+#define MAKE_CLK_SRC(src_grp, index) (((uint8_t)(src_grp) << 4) | (index))
+
+typedef enum _clock_sources {
+    clk_src_osc24m = MAKE_CLK_SRC(CLK_SRC_GROUP_COMMON, 0),
+    clk_src_pll0_clk0 = MAKE_CLK_SRC(CLK_SRC_GROUP_COMMON, 1),
+    clk_adc_src_ahb0 = MAKE_CLK_SRC(CLK_SRC_GROUP_ADC, 0),
+    clk_adc_src_ana0 = MAKE_CLK_SRC(CLK_SRC_GROUP_ADC, 1),
+    clk_i2s_src_aud0 = MAKE_CLK_SRC(CLK_SRC_GROUP_I2S, 0),
+    clk_src_invalid = MAKE_CLK_SRC(CLK_SRC_GROUP_INVALID, 15),
+} clk_src_t;
+"#;
+        let muxes = parse_clock_muxes(content);
+        let names: Vec<&str> = muxes.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["ADC", "I2S"]);
+        let adc: Vec<(&str, usize)> = muxes[0]
+            .sources
+            .iter()
+            .map(|s| (s.name.as_str(), s.index))
+            .collect();
+        assert_eq!(adc, [("AHB0", 0), ("ANA0", 1)]);
+        assert_eq!(muxes[1].sources[0].name, "AUD0");
+    }
+
+    #[test]
+    fn plls_and_masks() {
+        let sources = parse_clock_sources(
+            "typedef enum { clock_source_osc0_clk0 = 0, clock_source_pll0_clk0 = 1, \
+             clock_source_pll0_clk1 = 2, clock_source_pll1_clk0 = 3 } clock_source_t;",
+        )
+        .unwrap();
+        let content = r#"
+/* DIV register group index macro definition */
+#define PLLCTLV2_PLL_DIV_DIV0 (0UL)
+#define PLLCTLV2_PLL_DIV_DIV1 (1UL)
+
+/* PLL register group index macro definition */
+#define PLLCTLV2_PLL_PLL0 (0UL)
+#define PLLCTLV2_PLL_PLL1 (1UL)
+
+/*
+ * DIV (RW)
+ */
+#define PLLCTLV2_PLL_DIV_DIV_MASK (0x3FU)
+#define PLLCTLV2_PLL_DIV_DIV_SHIFT (0U)
+"#;
+        let plls = parse_plls(content, &sources);
+        assert_eq!(plls.len(), 2);
+        assert_eq!(plls[0].name, "PLL0");
+        assert_eq!(plls[0].outputs, ["PLL0_CLK0", "PLL0_CLK1"]);
+        assert_eq!(plls[1].outputs, ["PLL1_CLK0"]);
+        assert_eq!(plls[1].div_width, Some(6));
+
+        let sysctl =
+            "#define SYSCTL_CLOCK_MUX_MASK (0xF00U)\n#define SYSCTL_CLOCK_DIV_MASK (0xFFU)\n";
+        assert_eq!(mask_width(sysctl, "SYSCTL_CLOCK_MUX_MASK"), Some(4));
+        assert_eq!(mask_width(sysctl, "SYSCTL_CLOCK_DIV_MASK"), Some(8));
+        assert_eq!(mask_width(sysctl, "SYSCTL_CLOCK_LOC_BUSY_MASK"), None);
+    }
+}
@@ -0,0 +1,197 @@
+//! `hpm-data-gen diff <kind> [<PERIPHERAL>]`: structural comparison of register versions
+//!
+//! Each version is flattened to `REG` and `REG.FIELD` entries, descriptions and the names of
+//! fieldsets and enums are ignored. With a peripheral name the registers extracted from every
+//! SVD are compared as well.
+
+use std::collections::BTreeMap;
+
+use chiptool::ir::{BitOffset, BlockItemInner, IR};
+
+/// `REG` or `BLOCK.REG.FIELD` => layout
+type Flat = BTreeMap<String, String>;
+
+fn flatten_block(ir: &IR, block: &str, prefix: &str, flat: &mut Flat) {
+    let Some(b) = ir.blocks.get(block) else {
+        return;
+    };
+    for item in &b.items {
+        let path = format!("{}{}", prefix, item.name);
+        let array = item
+            .array
+            .as_ref()
+            .map(|a| format!(", array {:?}", a))
+            .unwrap_or_default();
+        match &item.inner {
+            BlockItemInner::Block(inner) => {
+                flat.insert(
+                    path.clone(),
+                    format!("block at 0x{:x}{}", item.byte_offset, array),
+                );
+                flatten_block(ir, &inner.block, &format!("{}.", path), flat);
+            }
+            BlockItemInner::Register(register) => {
+                flat.insert(
+                    path.clone(),
+                    format!(
+                        "0x{:x}, {:?}, {} bits{}",
+                        item.byte_offset, register.access, register.bit_size, array
+                    ),
+                );
+                let Some(fieldset) = register.fieldset.as_ref().and_then(|f| ir.fieldsets.get(f))
+                else {
+                    continue;
+                };
+                for field in &fieldset.fields {
+                    let mut layout = match &field.bit_offset {
+                        BitOffset::Regular(offset) => format!("bit {}", offset),
+                        BitOffset::Cursed(ranges) => format!("bits {:?}", ranges),
+                    };
+                    layout.push_str(&format!(", {} bits", field.bit_size));
+                    if let Some(array) = &field.array {
+                        layout.push_str(&format!(", array {:?}", array));
+                    }
+                    if let Some(e) = field.enumm.as_ref().and_then(|e| ir.enums.get(e)) {
+                        let variants: Vec<_> = e
+                            .variants
+                            .iter()
+                            .map(|v| format!("{}={}", v.name, v.value))
+                            .collect();
+                        layout.push_str(&format!(", enum {}", variants.join(" ")));
+                    }
+                    flat.insert(format!("{}.{}", path, field.name), layout);
+                }
+            }
+        }
+    }
+}
+
+/// Blocks not used by another block are the peripherals
+fn flatten(ir: &IR) -> Flat {
+    let mut flat = Flat::new();
+    for name in ir.blocks.keys() {
+        let nested = ir.blocks.values().any(|b| {
+            b.items
+                .iter()
+                .any(|i| matches!(&i.inner, BlockItemInner::Block(inner) if &inner.block == name))
+        });
+        if !nested {
+            flatten_block(ir, name, "", &mut flat);
+        }
+    }
+    flat
+}
+
+/// Entries of `b` missing in `a`, and entries of both with a different layout
+fn differences<'a>(a: &Flat, b: &'a Flat) -> (Vec<&'a String>, Vec<&'a String>) {
+    let missing = b.keys().filter(|k| !a.contains_key(*k)).collect();
+    let changed = b
+        .iter()
+        .filter(|(k, v)| a.get(*k).is_some_and(|other| other != *v))
+        .map(|(k, _)| k)
+        .collect();
+    (missing, changed)
+}
+
+/// `a` has every entry of `b`, with the same layout
+fn is_superset(a: &Flat, b: &Flat) -> bool {
+    let (missing, changed) = differences(a, b);
+    missing.is_empty() && changed.is_empty()
+}
+
+fn print_diff(a_name: &str, a: &Flat, b_name: &str, b: &Flat) {
+    println!("    {} -> {}:", a_name, b_name);
+    let (added, changed) = differences(a, b);
+    let (removed, _) = differences(b, a);
+    for k in removed {
+        println!("        - {}: {}", k, a[k]);
+    }
+    for k in added {
+        println!("        + {}: {}", k, b[k]);
+    }
+    for k in changed {
+        println!("        ~ {}: {} => {}", k, a[k], b[k]);
+    }
+}
+
+fn compare(title: &str, irs: &BTreeMap<String, IR>) {
+    println!("{}: {:?}", title, irs.keys().collect::<Vec<_>>());
+
+    // identical versions share a class, named after the first of them
+    let mut classes: Vec<(Vec<&str>, Flat)> = Vec::new();
+    for (name, ir) in irs {
+        let flat = flatten(ir);
+        match classes.iter_mut().find(|(_, f)| *f == flat) {
+            Some((names, _)) => names.push(name),
+            None => classes.push((vec![name], flat)),
+        }
+    }
+
+    for (names, _) in classes.iter().filter(|(names, _)| names.len() > 1) {
+        println!("    identical: {}", names.join(" = "));
+    }
+
+    for (a_names, a) in &classes {
+        for (b_names, b) in &classes {
+            if a_names != b_names && is_superset(a, b) {
+                println!("    superset: {} > {}", a_names[0], b_names[0]);
+            }
+        }
+    }
+
+    for (i, (a_names, a)) in classes.iter().enumerate() {
+        for (b_names, b) in &classes[i + 1..] {
+            print_diff(a_names[0], a, b_names[0], b);
+        }
+    }
+
+    // a class contained in another one is served by it
+    let mut suggested = Vec::new();
+    for (names, flat) in &classes {
+        let covered_by = classes
+            .iter()
+            .any(|(other, f)| other != names && is_superset(f, flat));
+        if !covered_by {
+            let covers: Vec<_> = classes
+                .iter()
+                .filter(|(other, f)| other != names && is_superset(flat, f))
+                .flat_map(|(other, _)| other.iter().copied())
+                .chain(names[1..].iter().copied())
+                .collect();
+            suggested.push(if covers.is_empty() {
+                names[0].to_string()
+            } else {
+                format!("{} (covers {})", names[0], covers.join(", "))
+            });
+        }
+    }
+    println!("    suggested versions: {}", suggested.join(", "));
+}
+
+fn load_versions(kind: &str) -> anyhow::Result<BTreeMap<String, IR>> {
+    let mut versions = BTreeMap::new();
+    for f in glob::glob(&format!("data/registers/{}_*.yaml", kind))? {
+        let f = f?;
+        let name = f.file_stem().unwrap().to_string_lossy().to_string();
+        let version = name.strip_prefix(&format!("{}_", kind)).unwrap();
+        let ir: IR = serde_yaml::from_str(&std::fs::read_to_string(&f)?)
+            .map_err(|e| anyhow::anyhow!("failed to parse {f:?}: {e:?}"))?;
+        versions.insert(version.to_string(), ir);
+    }
+    anyhow::ensure!(!versions.is_empty(), "No data/registers/{}_*.yaml", kind);
+    Ok(versions)
+}
+
+pub fn diff(args: &[String]) -> anyhow::Result<()> {
+    let [kind, rest @ ..] = args else {
+        anyhow::bail!("Usage: hpm-data-gen diff <kind> [<PERIPHERAL>]");
+    };
+
+    compare(kind, &load_versions(kind)?);
+
+    if let [peripheral] = rest {
+        compare(peripheral, &crate::extract::extract_irs(peripheral)?);
+    }
+
+    Ok(())
+}
@@ -1,55 +1,229 @@
-use std::{collections::HashMap, path::Path};
+//! DMAMUX requests from sdk_code `HPM_DMA_SRC_*` defines, with data/dmamux as an override layer
 
-fn parse_signal(signal_name: &str, periph_name: &str) -> String {
-    if signal_name.contains("_") {
-        let suffix = signal_name.split("_").last().unwrap();
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
-        if signal_name.starts_with("GPTMR") || signal_name.starts_with("NTMR") {
-            format!("CH{}", suffix)
-        } else {
-            suffix.to_string()
+use crate::family::Family;
+
+/// Entry of data/dmamux/signals.yaml, for the request names starting with its prefix
+#[derive(Debug, serde::Deserialize)]
+struct SignalPrefix {
+    /// signal of `<PREFIX><n>`, a name without suffix
+    #[serde(default)]
+    signal: Option<String>,
+    /// suffix => signal of `<PREFIX><n>_<SUFFIX>`
+    #[serde(default)]
+    signals: BTreeMap<String, String>,
+}
+
+struct SignalTable {
+    prefixes: BTreeMap<String, SignalPrefix>,
+}
+
+impl SignalTable {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", path, e))?;
+        let prefixes = serde_yaml::from_str(&content)?;
+        Ok(Self { prefixes })
+    }
+
+    /// UART0_RX => (UART0, RX), ESC_SYNC0 => (ESC, SYNC0)
+    fn lookup(&self, name: &str) -> Option<(String, String)> {
+        // the longest declared prefix
+        let (prefix, rule) = self
+            .prefixes
+            .iter()
+            .filter(|(prefix, _)| name.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())?;
+        let rest = &name[prefix.len()..];
+        let (instance, suffix) = match rest.split_once('_') {
+            Some((instance, suffix)) => (instance, Some(suffix)),
+            None => (rest, None),
+        };
+        if !instance.chars().all(|c| c.is_ascii_digit()) {
+            return None;
         }
-    } else if signal_name.starts_with("I2C") {
-        "GLOBAL".to_string()
-    } else {
-        periph_name.to_string()
+        let signal = match suffix {
+            Some(suffix) => rule.signals.get(suffix)?,
+            None => rule.signal.as_ref()?,
+        };
+        Some((format!("{}{}", prefix, instance), signal.clone()))
     }
 }
 
-pub fn handle_chip_dmamux_include<P: AsRef<Path>>(
-    path: P,
+// #define HPM_DMA_SRC_GPTMR0_0 (0x0UL) /* GPTMR0 channel 0 */
+fn parse_requests(content: &str) -> BTreeMap<String, u8> {
+    let src_pattern = regex::Regex::new(r"#define\s+HPM_DMA_SRC_(\w+)\s+\(0x([0-9A-Fa-f]+)UL\)")
+        .expect("Invalid regex");
+    src_pattern
+        .captures_iter(content)
+        .map(|cap| {
+            (
+                cap.get(1).unwrap().as_str().to_string(),
+                u8::from_str_radix(cap.get(2).unwrap().as_str(), 16).unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn load_sdk_requests<P: AsRef<Path>>(
+    data_dir: P,
+    family: &Family,
+) -> anyhow::Result<BTreeMap<String, u8>> {
+    let header_path = family.header(&data_dir, &family.headers.dmamux);
+    let content = std::fs::read_to_string(&header_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", &header_path, e))?;
+
+    let requests = parse_requests(&content);
+    anyhow::ensure!(
+        !requests.is_empty(),
+        "No HPM_DMA_SRC_ defines in {:?}",
+        header_path
+    );
+
+    Ok(requests)
+}
+
+pub fn add_dmamux_from_sdk<P: AsRef<Path>>(
+    data_dir: P,
+    meta_yaml_path: &Path,
+    family: &Family,
     chip: &mut hpm_data_serde::Chip,
 ) -> anyhow::Result<()> {
-    let meta_yaml_path = path.as_ref();
+    let table = SignalTable::load(&data_dir.as_ref().join("dmamux/signals.yaml"))?;
+    let sdk_requests = load_sdk_requests(&data_dir, family)?;
 
     for core in &mut chip.cores {
+        // the SDK header describes the request inputs of the one DMAMUX, every SVD has a single
+        // DMAMUX, feeding both HDMA and XDMA on chips with two DMA controllers
+        let dmamuxes: Vec<&str> = core
+            .peripherals
+            .iter()
+            .filter(|p| p.registers.as_ref().is_some_and(|r| r.kind == "dmamux"))
+            .map(|p| p.name.as_str())
+            .collect();
+        let dmamux = match dmamuxes[..] {
+            [dmamux] => dmamux.to_string(),
+            _ => anyhow::bail!(
+                "{}: expected one DMAMUX peripheral, found {:?}",
+                chip.name,
+                dmamuxes
+            ),
+        };
+
+        let mut requests = sdk_requests.clone();
+
         if let Some(include_path) = core.include_dmamux.take() {
             let dma_yaml_path = meta_yaml_path.parent().unwrap().join(&include_path);
-            let content = std::fs::read_to_string(&dma_yaml_path)?;
-            let dmamux: HashMap<String, usize> = serde_yaml::from_str(&content)?;
-            // println!("dma_channels: {:#?}", dmamux);
-
-            for (signal_name, request_no) in dmamux {
-                for periph in core.peripherals.iter_mut() {
-                    let signal_periph_prefix =
-                        signal_name.split('_').next().expect("empty signal_name");
-                    if periph.name == signal_periph_prefix {
-                        // println!("matches signal_name: {:#?}", signal_name);
-
-                        let signal = parse_signal(&signal_name, &periph.name);
-
-                        periph.dma_channels.push(
-                            hpm_data_serde::chip::core::peripheral::DmaChannel {
-                                signal: signal.clone(),
-                                dmamux: Some("DMAMUX".to_string()),
-                                request: request_no as u8,
-                            },
-                        );
-                    }
+            let content = std::fs::read_to_string(&dma_yaml_path)
+                .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", &dma_yaml_path, e))?;
+            // only the differences to the SDK, `NAME: ~` removes an SDK request
+            let overrides: HashMap<String, Option<u8>> = serde_yaml::from_str(&content)?;
+
+            for (name, request) in overrides {
+                match (request, sdk_requests.get(&name)) {
+                    (Some(request), Some(&sdk)) if request == sdk => anyhow::bail!(
+                        "{}: DMA request {} is {} in SDK as well, remove it",
+                        include_path,
+                        name,
+                        request
+                    ),
+                    (Some(request), Some(sdk)) => println!(
+                        "    {}: DMA request {} is {} in {}, {} in SDK",
+                        chip.name, name, request, include_path, sdk
+                    ),
+                    (Some(request), None) => println!(
+                        "    {}: DMA request {} ({}) from {} is not in SDK",
+                        chip.name, name, request, include_path
+                    ),
+                    (None, None) => anyhow::bail!(
+                        "{}: removes DMA request {} which is not in SDK",
+                        include_path,
+                        name
+                    ),
+                    (None, Some(_)) => {}
                 }
+                match request {
+                    Some(request) => requests.insert(name, request),
+                    None => requests.remove(&name),
+                };
             }
         }
+
+        let mut unmatched = vec![];
+        for (name, request) in requests {
+            let periph = table.lookup(&name).and_then(|(periph_name, signal)| {
+                let periph = core
+                    .peripherals
+                    .iter_mut()
+                    .find(|p| p.name == periph_name)?;
+                Some((periph, signal))
+            });
+            let Some((periph, signal)) = periph else {
+                unmatched.push(name);
+                continue;
+            };
+            periph
+                .dma_channels
+                .push(hpm_data_serde::chip::core::peripheral::DmaChannel {
+                    signal,
+                    dmamux: Some(dmamux.clone()),
+                    request,
+                });
+        }
+        for periph in &mut core.peripherals {
+            periph.dma_channels.sort_by_key(|ch| ch.request);
+        }
+
+        if !unmatched.is_empty() {
+            println!(
+                "    {}: DMA requests matching no peripheral: {:?}",
+                chip.name, unmatched
+            );
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dma_src_defines() {
+        let content = r#"
+#define HPM_DMA_SRC_GPTMR0_0                               (0x0UL) /* GPTMR0 channel 0 */
+#define HPM_DMA_SRC_GPTMR0_1                               (0x1UL) /* GPTMR0 channel 1 */
+#define HPM_DMA_SRC_UART0_RX                               (0x8UL) /* UART0 Receive */
+#define HPM_DMA_SRC_UART0_TX                               (0x9UL) /* UART0 Transmit */
+#define HPM_DMA_SRC_MCAN0                                  (0x30UL) /* MCAN0 */
+"#;
+        let requests = parse_requests(content);
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests["GPTMR0_1"], 1);
+        assert_eq!(requests["UART0_TX"], 9);
+        assert_eq!(requests["MCAN0"], 0x30);
+    }
+
+    #[test]
+    fn signal_table() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../data/dmamux/signals.yaml");
+        let table = SignalTable::load(&path).unwrap();
+        let lookup = |name| table.lookup(name);
+        let some = |p: &str, s: &str| Some((p.to_string(), s.to_string()));
+
+        assert_eq!(lookup("UART0_RX"), some("UART0", "RX"));
+        assert_eq!(lookup("UART10_TX"), some("UART10", "TX"));
+        assert_eq!(lookup("GPTMR2_3"), some("GPTMR2", "CH3"));
+        assert_eq!(lookup("I2C1"), some("I2C1", "GLOBAL"));
+        assert_eq!(lookup("ACMP0_1"), some("ACMP0", "CH1"));
+        assert_eq!(lookup("ESC_SYNC0"), some("ESC", "SYNC0"));
+        // undeclared prefix, suffix, or an instance that is not a number
+        assert_eq!(lookup("MOT0_0"), None);
+        assert_eq!(lookup("UART0_CTS"), None);
+        assert_eq!(lookup("ESC_RESET"), None);
+        assert_eq!(lookup("UARTX_RX"), None);
+    }
+}
@@ -0,0 +1,385 @@
+//! `hpm-data-gen extract <PERIPHERAL>...`: register YAML from svd/*.xml, with transforms/<PERIPHERAL>.yaml
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chiptool::ir::{BitOffset, BlockItemInner, IR};
+use chiptool::transform;
+use svd_parser::svd::{self, RegisterCluster};
+
+/// transforms/<PERIPHERAL>.yaml, every field is optional
+#[derive(Debug, Default, serde::Deserialize)]
+struct Config {
+    /// register kind, defaults to the SVD group name in lower case
+    kind: Option<String>,
+    /// version => SVDs, e.g. `common: [HPM5301, HPM5361]`, overriding the series version
+    #[serde(default)]
+    versions: BTreeMap<String, Vec<String>>,
+    /// SVDs not extracted, their chips share a version of another SVD or leave the peripheral out
+    #[serde(default)]
+    skip: Vec<String>,
+    /// chiptool transforms, applied in order
+    #[serde(default)]
+    transforms: Vec<transform::Transform>,
+}
+
+impl Config {
+    fn load(peripheral: &str) -> anyhow::Result<Self> {
+        let path = Path::new("transforms").join(format!("{}.yaml", peripheral));
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", path, e))?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {:?}: {}", path, e))
+    }
+
+    fn version(&self, svd: &str) -> Option<&str> {
+        self.versions
+            .iter()
+            .find(|(_, svds)| svds.iter().any(|s| s == svd))
+            .map(|(version, _)| version.as_str())
+    }
+}
+
+/// HPM6E80 => v6e
+fn series_version(svd: &str) -> anyhow::Result<String> {
+    match svd.strip_prefix("HPM").and_then(|s| s.get(..2)) {
+        Some(series) if series.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            Ok(format!("v{}", series.to_ascii_lowercase()))
+        }
+        _ => anyhow::bail!(
+            "{}: no series in the SVD name, assign it a version in transforms/",
+            svd
+        ),
+    }
+}
+
+/// Field semantics chiptool does not keep, SVD `modifiedWriteValues` and `readAction`
+#[derive(Debug, Default, Clone, PartialEq)]
+struct FieldAccess {
+    modified_write_values: Option<&'static str>,
+    read_action: Option<&'static str>,
+}
+
+struct Extracted {
+    kind: String,
+    ir: IR,
+    /// (block, register) => reset value, zero is left out
+    reset_values: BTreeMap<(String, String), u64>,
+    /// (fieldset, field) => access, plain read-write is left out
+    field_access: BTreeMap<(String, String), FieldAccess>,
+}
+
+// the mapping in `list` of `key` named `name`
+fn find_named<'a>(
+    value: &'a mut serde_yaml::Value,
+    key: &str,
+    list: &str,
+    name: &str,
+) -> Option<&'a mut serde_yaml::Mapping> {
+    value
+        .get_mut(key)?
+        .get_mut(list)?
+        .as_sequence_mut()?
+        .iter_mut()
+        .find(|i| i.get("name").and_then(|n| n.as_str()) == Some(name))?
+        .as_mapping_mut()
+}
+
+impl Extracted {
+    /// chiptool drops these, they are added as `reset_value` of items, and
+    /// `modified_write_values` and `read_action` of fields
+    fn to_yaml(&self) -> anyhow::Result<String> {
+        let mut value = serde_yaml::to_value(&self.ir)?;
+        for ((block, register), reset_value) in &self.reset_values {
+            let key = format!("block/{}", block);
+            if let Some(item) = find_named(&mut value, &key, "items", register) {
+                item.insert("reset_value".into(), (*reset_value).into());
+            }
+        }
+        for ((fieldset, field), access) in &self.field_access {
+            let key = format!("fieldset/{}", fieldset);
+            if let Some(field) = find_named(&mut value, &key, "fields", field) {
+                if let Some(v) = access.modified_write_values {
+                    field.insert("modified_write_values".into(), v.into());
+                }
+                if let Some(v) = access.read_action {
+                    field.insert("read_action".into(), v.into());
+                }
+            }
+        }
+        Ok(serde_yaml::to_string(&value)?)
+    }
+}
+
+// offset from the peripheral base => registers
+fn svd_registers<'a>(
+    children: &'a [RegisterCluster],
+    base: u32,
+    out: &mut BTreeMap<u32, Vec<&'a svd::Register>>,
+) {
+    for child in children {
+        match child {
+            RegisterCluster::Register(r) => {
+                out.entry(base + r.address_offset).or_default().push(r);
+            }
+            RegisterCluster::Cluster(c) => svd_registers(&c.children, base + c.address_offset, out),
+        }
+    }
+}
+
+// registers are matched by offset, which transforms keep, and by name when they share one
+fn match_registers<'a>(
+    ir: &IR,
+    block: &str,
+    base: u32,
+    svd: &BTreeMap<u32, Vec<&'a svd::Register>>,
+    out: &mut BTreeMap<(String, String), &'a svd::Register>,
+) {
+    let Some(b) = ir.blocks.get(block) else {
+        return;
+    };
+    for item in &b.items {
+        let offset = base + item.byte_offset;
+        match &item.inner {
+            BlockItemInner::Register(_) => {
+                let registers = svd.get(&offset).map(Vec::as_slice).unwrap_or_default();
+                let register = match registers {
+                    [register] => *register,
+                    _ => match registers
+                        .iter()
+                        .find(|r| r.name.eq_ignore_ascii_case(&item.name))
+                    {
+                        Some(register) => *register,
+                        None => continue,
+                    },
+                };
+                out.entry((block.to_string(), item.name.clone()))
+                    .or_insert(register);
+            }
+            BlockItemInner::Block(inner) => match_registers(ir, &inner.block, offset, svd, out),
+        }
+    }
+}
+
+fn extract_from_svd(
+    svd_path: &Path,
+    peripheral: &str,
+    config: &Config,
+) -> anyhow::Result<Option<Extracted>> {
+    let xml = std::fs::read_to_string(svd_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", svd_path, e))?;
+    let device = svd_parser::parse(&xml)
+        .map_err(|e| anyhow::anyhow!("Failed to parse SVD: {:?}: {}", svd_path, e))?;
+
+    let Some(mut p) = device.peripherals.iter().find(|p| p.name == peripheral) else {
+        return Ok(None);
+    };
+    if let Some(base) = &p.derived_from {
+        p = device
+            .peripherals
+            .iter()
+            .find(|p| &p.name == base)
+            .ok_or_else(|| anyhow::anyhow!("derivedFrom peripheral {} not found", base))?;
+    }
+
+    let mut ir = IR::new();
+    chiptool::svd2ir::convert_peripheral(&mut ir, p)?;
+
+    // descriptions are wrapped in the SVD
+    let whitespace = regex::Regex::new("[ \n]+").expect("Invalid regex");
+    transform::map_descriptions(&mut ir, |d| whitespace.replace_all(d, " ").into_owned())?;
+
+    for t in &config.transforms {
+        t.run(&mut ir)?;
+    }
+    transform::sort::Sort {}.run(&mut ir)?;
+
+    let kind = config.kind.clone().unwrap_or_else(|| {
+        p.group_name
+            .clone()
+            .unwrap_or_else(|| p.name.trim_end_matches(char::is_numeric).to_string())
+            .to_ascii_lowercase()
+    });
+
+    let mut svd = BTreeMap::new();
+    svd_registers(p.registers.as_deref().unwrap_or_default(), 0, &mut svd);
+    let mut matched = BTreeMap::new();
+    for block in ir.blocks.keys() {
+        let nested = ir.blocks.values().any(|b| {
+            b.items
+                .iter()
+                .any(|i| matches!(&i.inner, BlockItemInner::Block(inner) if &inner.block == block))
+        });
+        if !nested {
+            match_registers(&ir, block, 0, &svd, &mut matched);
+        }
+    }
+
+    let mut reset_values = BTreeMap::new();
+    let mut field_access = BTreeMap::new();
+    for ((block, item), register) in matched {
+        if let Some(reset_value) = register.properties.reset_value.filter(|&v| v != 0) {
+            reset_values.insert((block.clone(), item.clone()), reset_value);
+        }
+
+        let fieldset = ir.blocks[&block]
+            .items
+            .iter()
+            .find(|i| i.name == item)
+            .and_then(|i| match &i.inner {
+                BlockItemInner::Register(r) => r.fieldset.as_ref(),
+                BlockItemInner::Block(_) => None,
+            })
+            .and_then(|f| ir.fieldsets.get_key_value(f));
+        let Some((fieldset_name, fieldset)) = fieldset else {
+            continue;
+        };
+        // fields are matched by bit offset
+        for svd_field in register.fields.as_deref().unwrap_or_default() {
+            let access = FieldAccess {
+                modified_write_values: svd_field.modified_write_values.map(|v| v.as_str()),
+                read_action: svd_field.read_action.map(|v| v.as_str()),
+            };
+            if access == FieldAccess::default() {
+                continue;
+            }
+            let field = fieldset.fields.iter().find(|f| {
+                matches!(f.bit_offset, BitOffset::Regular(o) if o == svd_field.bit_range.offset)
+            });
+            if let Some(field) = field {
+                field_access.insert((fieldset_name.clone(), field.name.clone()), access);
+            }
+        }
+    }
+
+    Ok(Some(Extracted {
+        kind,
+        ir,
+        reset_values,
+        field_access,
+    }))
+}
+
+/// SVD name => registers of `peripheral`, for every SVD that has it
+fn extract_from_svds(
+    peripheral: &str,
+    config: &Config,
+) -> anyhow::Result<BTreeMap<String, Extracted>> {
+    let mut svd_paths: Vec<_> = glob::glob("svd/HPM*_svd.xml")?.collect::<Result<_, _>>()?;
+    svd_paths.sort();
+
+    let mut extracted = BTreeMap::new();
+    for svd_path in &svd_paths {
+        let name = svd_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .strip_suffix("_svd.xml")
+            .unwrap()
+            .to_string();
+        match extract_from_svd(svd_path, peripheral, config)? {
+            Some(e) => {
+                extracted.insert(name, e);
+            }
+            None => println!("    {}: no peripheral {}", name, peripheral),
+        }
+    }
+    anyhow::ensure!(
+        !extracted.is_empty(),
+        "Peripheral {} not found in any SVD",
+        peripheral
+    );
+
+    Ok(extracted)
+}
+
+/// SVD name => registers of `peripheral`, with transforms/<PERIPHERAL>.yaml applied
+pub fn extract_irs(peripheral: &str) -> anyhow::Result<BTreeMap<String, IR>> {
+    let config = Config::load(peripheral)?;
+    Ok(extract_from_svds(peripheral, &config)?
+        .into_iter()
+        .map(|(name, e)| (name, e.ir))
+        .collect())
+}
+
+fn extract_peripheral(peripheral: &str) -> anyhow::Result<()> {
+    let config = Config::load(peripheral)?;
+    let extracted: BTreeMap<String, (String, String)> = extract_from_svds(peripheral, &config)?
+        .into_iter()
+        .filter(|(name, _)| !config.skip.contains(name))
+        .map(|(name, e)| Ok((name, (e.kind.clone(), e.to_yaml()?))))
+        .collect::<anyhow::Result<_>>()?;
+    anyhow::ensure!(
+        !extracted.is_empty(),
+        "every SVD with {} is skipped in transforms/{}.yaml",
+        peripheral,
+        peripheral
+    );
+
+    let first_yaml = &extracted.values().next().unwrap().1;
+    let all_same = extracted.values().all(|(_, yaml)| yaml == first_yaml);
+
+    // version => SVDs that extract to it
+    let mut versions: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for name in extracted.keys() {
+        let version = match config.version(name) {
+            Some(version) => version.to_string(),
+            None if all_same => "common".to_string(),
+            None => series_version(name)?,
+        };
+        versions.entry(version).or_default().push(name);
+    }
+
+    std::fs::create_dir_all("data/registers")?;
+    for (version, svds) in &versions {
+        let (kind, yaml) = &extracted[svds[0]];
+        let differing: Vec<_> = svds[1..]
+            .iter()
+            .filter(|s| extracted[**s] != extracted[svds[0]])
+            .collect();
+        anyhow::ensure!(
+            differing.is_empty(),
+            "{} of {:?} differs from {}, assign them a version in transforms/{}.yaml",
+            peripheral,
+            differing,
+            svds[0],
+            peripheral
+        );
+
+        let path = format!("data/registers/{}_{}.yaml", kind, version);
+        std::fs::write(&path, yaml)?;
+        println!("    {}: {:?}", path, svds);
+    }
+
+    Ok(())
+}
+
+pub fn extract(peripherals: &[String]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !peripherals.is_empty(),
+        "Usage: hpm-data-gen extract <PERIPHERAL>..."
+    );
+    for peripheral in peripherals {
+        println!("extracting {}", peripheral);
+        extract_peripheral(peripheral)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_versions() {
+        assert_eq!(series_version("HPM5361").unwrap(), "v53");
+        assert_eq!(series_version("HPM6E80").unwrap(), "v6e");
+        assert_eq!(series_version("HPM6280").unwrap(), "v62");
+        assert!(series_version("PLIC").is_err());
+        assert!(series_version("HPM").is_err());
+        assert!(series_version("HPM_6").is_err());
+    }
+}
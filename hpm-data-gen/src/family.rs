@@ -0,0 +1,96 @@
+//! chip family SDK layout, from data/family/sdk
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Family {
+    /// soc directory, relative to HPM_SDK_BASE
+    pub soc: String,
+    /// pinmux tool export, relative to data dir
+    pub pinmux: String,
+    pub headers: Headers,
+    /// pad prefixes that exist in hpm_ioc_regs.h but not on the chip
+    #[serde(default)]
+    pub exclude_pads: Vec<String>,
+    #[serde(default)]
+    pub sysctl: Sysctl,
+    /// XPI0 NOR boot layout, from the SDK flash linker scripts
+    pub boot: Option<hpm_data_serde::chip::Boot>,
+    /// vendor SVD of the series, relative to the repository root
+    pub svd: Option<String>,
+    /// SDK and SVD peripheral name => peripheral name, for blocks named differently here
+    #[serde(default)]
+    pub base_renames: HashMap<String, String>,
+}
+
+/// SDK headers, relative to the soc directory
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Headers {
+    pub sysctl: String,
+    pub ioc: String,
+    pub iomux: String,
+    pub pmic_iomux: String,
+    /// not every soc has a battery domain iomux header
+    pub batt_iomux: Option<String>,
+    /// required when the chip has TRGM
+    pub trgmmux: Option<String>,
+    /// `clock_source_t`
+    pub sysctl_drv: String,
+    /// `clk_src_t`, `MAKE_CLK_SRC` source groups
+    pub clock_drv: String,
+    /// PLL and post divider indices
+    pub pllctl: String,
+    /// `IRQn_*`, older SDKs define them in hpm_soc.h next to it
+    pub irq: String,
+    /// `HPM_DMA_SRC_*`
+    pub dmamux: String,
+    /// `HPM_*_BASE`
+    pub soc: String,
+    /// `<KIND>_SOC_*` parameters and `HPM_IP_FEATURE_*` flags
+    pub soc_feature: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Sysctl {
+    /// peripheral name prefix => SDK resource name prefix, on top of the common table
+    #[serde(default)]
+    pub names: HashMap<String, String>,
+    /// resources and clocks use peripheral names as is
+    #[serde(default)]
+    pub plain_names: bool,
+    /// SDK clock name => peripheral clock name
+    #[serde(default)]
+    pub clock_renames: HashMap<String, String>,
+    /// clock node => maximum frequency in Hz, from the datasheet
+    #[serde(default)]
+    pub max_frequency: HashMap<String, u32>,
+}
+
+impl Family {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path.as_ref(), e))?;
+        let family: Family = serde_yaml::from_str(&content)?;
+        Ok(family)
+    }
+
+    fn sdk_base(data_dir: &Path) -> PathBuf {
+        std::env::var("HPM_SDK_BASE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| data_dir.parent().unwrap().join("hpm_sdk"))
+    }
+
+    /// Full path of a soc header
+    pub fn header<P: AsRef<Path>>(&self, data_dir: P, header: &str) -> PathBuf {
+        Self::sdk_base(data_dir.as_ref())
+            .join(&self.soc)
+            .join(header)
+    }
+
+    pub fn pinmux_path<P: AsRef<Path>>(&self, data_dir: P) -> PathBuf {
+        data_dir.as_ref().join(&self.pinmux)
+    }
+}
@@ -0,0 +1,162 @@
+//! parse per-soc parameters and `HPM_IP_FEATURE_*` flags from sdk_code hpm_soc_feature.h
+
+use std::{collections::BTreeMap, path::Path};
+
+use crate::family::Family;
+
+// #define UART_SOC_FIFO_SIZE (16U), expressions and function-like macros are skipped
+fn parse_defines(content: &str) -> BTreeMap<String, u32> {
+    let define_pattern = regex::Regex::new(
+        r"(?m)^\s*#define\s+(\w+)\s+\(?\s*(0[xX][0-9A-Fa-f]+|\d+)[uUlL]*\s*\)?\s*(?:/[/*].*)?$",
+    )
+    .expect("Invalid regex");
+    // #define HPM_IP_FEATURE_UART_RX_IDLE_DETECT, a flag without value is set
+    let flag_pattern = regex::Regex::new(r"(?m)^\s*#define\s+(HPM_IP_FEATURE_\w+)\s*(?:/[/*].*)?$")
+        .expect("Invalid regex");
+    let flags = flag_pattern
+        .captures_iter(content)
+        .map(|cap| (cap.get(1).unwrap().as_str().to_string(), 1));
+
+    define_pattern
+        .captures_iter(content)
+        .filter_map(|cap| {
+            let value = cap.get(2).unwrap().as_str();
+            let value = match value
+                .strip_prefix("0x")
+                .or_else(|| value.strip_prefix("0X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => value.parse().ok()?,
+            };
+            Some((cap.get(1).unwrap().as_str().to_string(), value))
+        })
+        .chain(flags)
+        .collect()
+}
+
+/// Names the SDK uses for a peripheral: its kind, and its name without the instance number,
+/// `UART0` of kind `uart` => `UART`, `HDMA` of kind `dma` => `HDMA`, `DMA`
+fn sdk_prefixes(p: &hpm_data_serde::chip::core::Peripheral) -> Vec<String> {
+    let mut prefixes = vec![p
+        .name
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .to_string()];
+    if let Some(registers) = &p.registers {
+        prefixes.push(registers.kind.to_ascii_uppercase());
+    }
+    prefixes
+}
+
+/// `<PREFIX>_SOC_<NAME>` defines go to the peripherals of that prefix, `HPM_IP_FEATURE_<PREFIX>_*`
+/// flags set to non-zero likewise. The rest is kept on the core.
+pub fn add_features_from_sdk<P: AsRef<Path>>(
+    data_dir: P,
+    family: &Family,
+    chip: &mut hpm_data_serde::Chip,
+) -> anyhow::Result<()> {
+    let header_file = family.header(&data_dir, &family.headers.soc_feature);
+    let content = std::fs::read_to_string(&header_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", &header_file, e))?;
+    let defines = parse_defines(&content);
+    anyhow::ensure!(!defines.is_empty(), "No defines in {:?}", header_file);
+
+    let mut assigned = 0;
+    for core in &mut chip.cores {
+        let prefixes: Vec<Vec<String>> = core.peripherals.iter().map(sdk_prefixes).collect();
+        // longest first, so that `ADC16_` is not taken for `ADC_`
+        let mut all_prefixes: Vec<&String> = prefixes.iter().flatten().collect();
+        all_prefixes.sort();
+        all_prefixes.dedup();
+        all_prefixes.sort_by_key(|p| std::cmp::Reverse(p.len()));
+
+        for (name, &value) in &defines {
+            if let Some(feature) = name.strip_prefix("HPM_IP_FEATURE_") {
+                if value == 0 {
+                    continue;
+                }
+                let prefix = all_prefixes
+                    .iter()
+                    .find(|p| feature.starts_with(&format!("{}_", p)));
+                match prefix {
+                    Some(prefix) => {
+                        for (p, peripheral_prefixes) in core.peripherals.iter_mut().zip(&prefixes) {
+                            if peripheral_prefixes.contains(prefix) {
+                                p.features.push(feature.to_string());
+                                assigned += 1;
+                            }
+                        }
+                    }
+                    None => core.features.push(feature.to_string()),
+                }
+                continue;
+            }
+
+            let split = name
+                .split_once("_SOC_")
+                .filter(|(prefix, _)| all_prefixes.iter().any(|p| p == prefix));
+            match split {
+                Some((prefix, param)) => {
+                    for (p, peripheral_prefixes) in core.peripherals.iter_mut().zip(&prefixes) {
+                        if peripheral_prefixes.iter().any(|p| p == prefix) {
+                            p.params.insert(param.to_string(), value);
+                            assigned += 1;
+                        }
+                    }
+                }
+                None => {
+                    core.params.insert(name.clone(), value);
+                }
+            }
+        }
+    }
+
+    println!(
+        "    Chip: {} soc feature defines: {}, assigned to peripherals: {}",
+        chip.name,
+        defines.len(),
+        assigned
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defines_and_flags() {
+        let content = r#"
+/*
+ * UART Section
+ */
+#define UART_SOC_FIFO_SIZE       (16U)
+#define UART_SOC_OVERSAMPLE_MAX  (31U) /* oversample rate: 1 - 31 */
+#define HPM_IP_FEATURE_UART_RX_IDLE_DETECT 1
+#define HPM_IP_FEATURE_UART_FINE_FIFO_THRLD
+
+/*
+ * DMA Section
+ */
+#define DMA_SOC_TRANSFER_WIDTH_MAX(x) (((x) == HPM_XDMA) ? DMA_TRANSFER_WIDTH_DOUBLE_WORD : DMA_TRANSFER_WIDTH_WORD)
+#define DMA_SOC_TRANSFER_PER_BURST_MAX(x) (((x) == HPM_XDMA) ? DMA_NUM_TRANSFER_PER_BURST_1024T : DMA_NUM_TRANSFER_PER_BURST_128T)
+#define DMA_SOC_BUS_NUM (1U)
+#define DMA_SOC_CHANNEL_NUM (32U)
+#define DMA_SOC_MAX_COUNT (2U)
+#define DMA_SOC_CHN_TO_DMAMUX_CHN(x, n) (((x) == HPM_XDMA) ? (DMA_SOC_CHANNEL_NUM + n) : (n))
+
+#define SOC_GPIO_INTR_MASK (0xFFFFU)
+"#;
+        let defines = parse_defines(content);
+        assert_eq!(defines["UART_SOC_FIFO_SIZE"], 16);
+        assert_eq!(defines["UART_SOC_OVERSAMPLE_MAX"], 31);
+        assert_eq!(defines["HPM_IP_FEATURE_UART_RX_IDLE_DETECT"], 1);
+        assert_eq!(defines["HPM_IP_FEATURE_UART_FINE_FIFO_THRLD"], 1);
+        assert_eq!(defines["DMA_SOC_CHANNEL_NUM"], 32);
+        assert_eq!(defines["SOC_GPIO_INTR_MASK"], 0xFFFF);
+        // expressions and function-like macros
+        assert!(!defines.contains_key("DMA_SOC_TRANSFER_WIDTH_MAX"));
+        assert!(!defines.contains_key("DMA_SOC_CHN_TO_DMAMUX_CHN"));
+        assert_eq!(defines.len(), 8);
+    }
+}
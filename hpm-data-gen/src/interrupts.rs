@@ -1,3 +1,108 @@
+//! interrupts from sdk_code `IRQn_*` defines, with data/interrupts as an override layer
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::family::Family;
+
+// #define IRQn_GPIO0_A 1 /* GPIO0_A IRQ */
+fn parse_interrupts(content: &str) -> BTreeMap<String, u8> {
+    let irq_pattern =
+        regex::Regex::new(r"#define\s+IRQn_(\w+)\s+\(?(\d+)U?L?\)?").expect("Invalid regex");
+    irq_pattern
+        .captures_iter(content)
+        .map(|cap| {
+            (
+                cap.get(1).unwrap().as_str().to_string(),
+                cap.get(2).unwrap().as_str().parse().unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn load_sdk_interrupts<P: AsRef<Path>>(
+    data_dir: P,
+    family: &Family,
+) -> anyhow::Result<BTreeMap<String, u8>> {
+    let mut header_path = family.header(&data_dir, &family.headers.irq);
+    if !header_path.exists() {
+        header_path = header_path.with_file_name("hpm_soc.h");
+    }
+    let content = std::fs::read_to_string(&header_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", &header_path, e))?;
+
+    let interrupts = parse_interrupts(&content);
+    anyhow::ensure!(
+        !interrupts.is_empty(),
+        "No IRQn_ defines in {:?}",
+        header_path
+    );
+
+    Ok(interrupts)
+}
+
+pub fn add_interrupts_from_sdk<P: AsRef<Path>>(
+    data_dir: P,
+    meta_yaml_path: &Path,
+    family: &Family,
+    chip: &mut hpm_data_serde::Chip,
+) -> anyhow::Result<()> {
+    let sdk_interrupts = load_sdk_interrupts(&data_dir, family)?;
+
+    for core in &mut chip.cores {
+        let mut interrupts = sdk_interrupts.clone();
+
+        if let Some(inc_path) = core.include_interrupts.take() {
+            let interrupts_yaml_path = meta_yaml_path.parent().unwrap().join(&inc_path);
+            let content = std::fs::read_to_string(&interrupts_yaml_path).map_err(|e| {
+                anyhow::anyhow!("Failed to read file: {:?}: {}", &interrupts_yaml_path, e)
+            })?;
+            // only the differences to the SDK, `NAME: ~` removes an SDK interrupt, for chips sharing
+            // the header of a bigger one
+            let overrides: HashMap<String, Option<u8>> = serde_yaml::from_str(&content)?;
+
+            for (name, number) in overrides {
+                match (number, sdk_interrupts.get(&name)) {
+                    (Some(number), Some(&sdk)) if number == sdk => anyhow::bail!(
+                        "{}: interrupt {} is {} in SDK as well, remove it",
+                        inc_path,
+                        name,
+                        number
+                    ),
+                    (Some(number), Some(sdk)) => println!(
+                        "    {}: interrupt {} is {} in {}, {} in SDK",
+                        chip.name, name, number, inc_path, sdk
+                    ),
+                    (Some(number), None) => println!(
+                        "    {}: interrupt {} ({}) from {} is not in SDK",
+                        chip.name, name, number, inc_path
+                    ),
+                    (None, None) => anyhow::bail!(
+                        "{}: removes interrupt {} which is not in SDK",
+                        inc_path,
+                        name
+                    ),
+                    (None, Some(_)) => {}
+                }
+                match number {
+                    Some(number) => interrupts.insert(name, number),
+                    None => interrupts.remove(&name),
+                };
+            }
+        }
+
+        let mut interrupts: Vec<(String, u8)> = interrupts.into_iter().collect();
+        interrupts.sort_by_key(|(_, number)| *number);
+
+        core.interrupts = interrupts
+            .into_iter()
+            .map(|(name, number)| hpm_data_serde::chip::core::Interrupt { name, number })
+            .collect();
+    }
+
+    Ok(())
+}
+
 fn parse_interrupt_signal(irq_name: &str) -> String {
     if irq_name.contains("_") {
         let suffix = irq_name.split("_").last().unwrap();
@@ -17,8 +122,10 @@ fn parse_interrupt_signal(irq_name: &str) -> String {
 pub fn fill_peripheral_interrupts(chip: &mut hpm_data_serde::Chip) -> anyhow::Result<()> {
     for core in chip.cores.iter_mut() {
         let interrupts = core.interrupts.clone();
+        let mut unmatched = Vec::new();
 
         for interrupt in &interrupts {
+            let mut matched = false;
             for periph in core.peripherals.iter_mut() {
                 if !interrupt.name.starts_with(&periph.name) {
                     continue;
@@ -39,9 +146,50 @@ pub fn fill_peripheral_interrupts(chip: &mut hpm_data_serde::Chip) -> anyhow::Re
                 });
 
                 periph.interrupts = Some(periph_ints);
+                matched = true;
+            }
+            if !matched {
+                unmatched.push(interrupt.name.as_str());
             }
         }
+        // interrupts of peripherals missing on this chip, remove them with `NAME: ~` in data/interrupts
+        if !unmatched.is_empty() {
+            println!(
+                "    {} {}: interrupts without peripheral: {:?}",
+                chip.name, core.name, unmatched
+            );
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn irqn_defines() {
+        let content = r#"
+#define IRQn_GPIO0_A                   1       /* GPIO0_A IRQ */
+#define IRQn_GPIO0_B                   2       /* GPIO0_B IRQ */
+#define IRQn_UART0                     13      /* UART0 IRQ */
+#define IRQn_ACMP_0                    62      /* ACMP[0] IRQ */
+#define IRQn_DEBUG1                    72      /* DEBUG[1] IRQ */
+"#;
+        let interrupts = parse_interrupts(content);
+        assert_eq!(interrupts.len(), 5);
+        assert_eq!(interrupts["GPIO0_A"], 1);
+        assert_eq!(interrupts["UART0"], 13);
+        assert_eq!(interrupts["ACMP_0"], 62);
+        assert_eq!(interrupts["DEBUG1"], 72);
+    }
+
+    #[test]
+    fn interrupt_signals() {
+        assert_eq!(parse_interrupt_signal("UART0"), "GLOBAL");
+        assert_eq!(parse_interrupt_signal("GPIO0_A"), "PA");
+        assert_eq!(parse_interrupt_signal("ACMP_1"), "CH1");
+        assert_eq!(parse_interrupt_signal("ESC_SYNC0"), "SYNC0");
+    }
+}
@@ -1,6 +1,12 @@
 use std::{collections::HashMap, path::Path};
 
+mod addresses;
+mod clock_tree;
+mod diff;
 mod dma;
+mod extract;
+mod family;
+mod features;
 mod interrupts;
 mod iomux;
 mod pinmux;
@@ -8,6 +14,7 @@ mod pins;
 mod registers;
 mod sysctl;
 mod trgmmux;
+mod validate;
 
 #[macro_export]
 macro_rules! regex {
@@ -56,9 +63,54 @@ impl Stopwatch {
     }
 }
 
+/// Chips dropped from the pipeline, with the stage they failed in
+#[derive(Default)]
+struct Failures {
+    failures: Vec<(String, &'static str, anyhow::Error)>,
+}
+
+impl Failures {
+    fn push(&mut self, chip: &str, stage: &'static str, error: anyhow::Error) {
+        println!("    {}: {} failed: {:#}", chip, stage, error);
+        self.failures.push((chip.to_string(), stage, error));
+    }
+
+    /// Run a stage on every chip, a chip that fails is reported and not processed further
+    fn run<F>(&mut self, chips: &mut Vec<hpm_data_serde::Chip>, stage: &'static str, mut f: F)
+    where
+        F: FnMut(&mut hpm_data_serde::Chip) -> anyhow::Result<()>,
+    {
+        chips.retain_mut(|chip| match f(chip) {
+            Ok(()) => true,
+            Err(e) => {
+                self.push(&chip.name, stage, e);
+                false
+            }
+        });
+    }
+
+    fn bail_if_any(self) -> anyhow::Result<()> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        eprintln!("Chips that could not be processed:");
+        for (chip, stage, error) in &self.failures {
+            eprintln!("    {}: {}: {:#}", chip, stage, error);
+        }
+        anyhow::bail!("{} chip(s) failed", self.failures.len())
+    }
+}
+
 fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("extract") => return extract::extract(&args[1..]),
+        Some("diff") => return diff::diff(&args[1..]),
+        _ => {}
+    }
+
     let mut stopwatch = Stopwatch::new();
 
     stopwatch.section("Parsing registers");
@@ -81,13 +133,18 @@ fn main() -> anyhow::Result<()> {
 
     std::fs::create_dir_all("build/data/chips")?;
 
+    let mut failures = Failures::default();
+
     let mut chips = vec![];
     for name in &chip_meta_files {
         let meta_yaml_path = data_dir.join(&format!("chips/{}.yaml", name));
-        let content = std::fs::read_to_string(&meta_yaml_path)?;
-        let chip: hpm_data_serde::Chip = serde_yaml::from_str(&content)?;
-
-        chips.push(chip);
+        let chip = std::fs::read_to_string(&meta_yaml_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_yaml::from_str::<hpm_data_serde::Chip>(&content)?));
+        match chip {
+            Ok(chip) => chips.push(chip),
+            Err(e) => failures.push(name, "Parsing chips", e),
+        }
     }
     chips.sort_by_key(|chip| chip.name.clone());
 
@@ -95,124 +152,75 @@ fn main() -> anyhow::Result<()> {
 
     let meta_yaml_path = data_dir.join("chips/DUMMY.yaml");
 
-    for chip in &mut chips {
-        for core in &mut chip.cores {
-            if let Some(inc_path) = core.include_interrupts.take() {
-                let interrupts_yaml_path = meta_yaml_path.parent().unwrap().join(&inc_path);
-                let content = std::fs::read_to_string(&interrupts_yaml_path)?;
-                let interrupts: HashMap<String, u8> = serde_yaml::from_str(&content)?;
-                let mut interrupts: Vec<(String, u8)> = interrupts.into_iter().collect();
-                interrupts.sort_by_key(|(_, number)| *number);
-
-                // println!("interrupts: {:#?}", interrupts);
-                for (name, number) in interrupts {
-                    core.interrupts
-                        .push(hpm_data_serde::chip::core::Interrupt { name, number });
-                }
+    let mut families = HashMap::new();
 
-                // Add Core Local Interrupt as Interrupt 0
-                core.interrupts.push(hpm_data_serde::chip::core::Interrupt {
-                    name: format!("CORE_LOCAL"),
-                    number: 0,
-                });
-            }
+    failures.run(&mut chips, "Handle includes", |chip| {
+        let family = handle_includes(&meta_yaml_path, chip)?;
+        families.insert(chip.name.clone(), family);
+        Ok(())
+    });
 
-            // append peripherals from includes
-            if let Some(inc_paths) = &mut core.include_peripherals.take() {
-                for inc_path in inc_paths {
-                    let peripheral_yaml_path = meta_yaml_path.parent().unwrap().join(&inc_path);
-                    let content = std::fs::read_to_string(&peripheral_yaml_path)?;
-                    let peripherals: Vec<hpm_data_serde::chip::core::Peripheral> =
-                        serde_yaml::from_str(&content)?;
-                    core.peripherals.extend(peripherals);
-                }
-            }
-
-            // generate dma channels
-            if let Some(gen) = &mut core.gen_dma_channels.take() {
-                assert!(
-                    core.dma_channels.is_empty(),
-                    "DMA channels already filled, cannot generate"
-                );
-
-                let &hdma_chs = gen.get("HDMA").expect("HDMA not found");
-                for ch in 0..hdma_chs {
-                    core.dma_channels
-                        .push(hpm_data_serde::chip::core::DmaChannels {
-                            name: format!("HDMA_CH{}", ch),
-                            dma: "HDMA".to_string(),
-                            channel: ch as _,
-                            dmamux_channel: ch as _,
-                        });
-                }
-
-                if let Some(&xdma_ch) = gen.get("XDMA") {
-                    for ch in 0..xdma_ch {
-                        core.dma_channels
-                            .push(hpm_data_serde::chip::core::DmaChannels {
-                                name: format!("XDMA_CH{}", ch),
-                                dma: "XDMA".to_string(),
-                                channel: ch as _,
-                                dmamux_channel: (hdma_chs + ch) as _, // xdma starts after hdma
-                            });
-                    }
-                }
-            }
-        }
-    }
+    stopwatch.section("Handle interrupts");
+    failures.run(&mut chips, "Handle interrupts", |chip| {
+        interrupts::add_interrupts_from_sdk(data_dir, &meta_yaml_path, &families[&chip.name], chip)
+    });
 
     stopwatch.section("Handle PINMUX");
 
-    for chip in &mut chips {
-        let pinmux_path = match &chip.name {
-            name if name.starts_with("HPM53") => data_dir.join("pinmux/HPM5361.json"),
-            name if name.starts_with("HPM62") => data_dir.join("pinmux/HPM6284.json"),
-            name if name.starts_with("HPM67") || name.starts_with("HPM64") => {
-                data_dir.join("pinmux/HPM6750.json")
-            }
-            name if name.starts_with("HPM68") => data_dir.join("pinmux/HPM6880.json"),
-            name if name.starts_with("HPM63") => data_dir.join("pinmux/HPM6364.json"),
-            name if name.starts_with("HPM6E") => data_dir.join("pinmux/HPM6E80.json"),
-            _ => {
-                println!("TODO: handle pinmux for {}", chip.name);
-                continue;
-            }
-        };
-
-        pinmux::handle_pinmux(&pinmux_path, chip)?;
-    }
+    failures.run(&mut chips, "Handle PINMUX", |chip| {
+        let pinmux_path = families[&chip.name].pinmux_path(data_dir);
+        pinmux::handle_pinmux(&pinmux_path, chip)
+    });
 
     stopwatch.section("Handle peripheral interrupts");
     // fill peripheral interrupts
-    for chip in &mut chips {
-        interrupts::fill_peripheral_interrupts(chip)?;
-    }
+    failures.run(&mut chips, "Handle peripheral interrupts", |chip| {
+        interrupts::fill_peripheral_interrupts(chip)
+    });
 
     stopwatch.section("Handle DMAMUX");
     // matching DMAMUX source to peripherals
-    for chip in &mut chips {
-        dma::handle_chip_dmamux_include(&meta_yaml_path, chip)?;
-    }
+    failures.run(&mut chips, "Handle DMAMUX", |chip| {
+        dma::add_dmamux_from_sdk(data_dir, &meta_yaml_path, &families[&chip.name], chip)
+    });
 
     stopwatch.section("Handle SYSCTL info");
-    for chip in &mut chips {
-        sysctl::add_sysctl_from_sdk(data_dir, chip)?;
-    }
+    failures.run(&mut chips, "Handle SYSCTL info", |chip| {
+        sysctl::add_sysctl_from_sdk(data_dir, &families[&chip.name], chip)
+    });
+
+    stopwatch.section("Handle clock tree");
+    failures.run(&mut chips, "Handle clock tree", |chip| {
+        clock_tree::add_clock_tree_from_sdk(data_dir, &families[&chip.name], chip)
+    });
 
     stopwatch.section("Handle iomux");
-    for chip in &mut chips {
-        iomux::add_iomux_from_sdk(data_dir, chip)?;
-    }
+    failures.run(&mut chips, "Handle iomux", |chip| {
+        iomux::add_iomux_from_sdk(data_dir, &families[&chip.name], chip)
+    });
 
     stopwatch.section("Handle IOC pins");
-    for chip in &mut chips {
-        pins::add_ioc_pins_from_sdk(data_dir, chip)?;
-    }
+    failures.run(&mut chips, "Handle IOC pins", |chip| {
+        pins::add_ioc_pins_from_sdk(data_dir, &families[&chip.name], chip)
+    });
 
     stopwatch.section("Handle TRGM MUX");
-    for chip in &mut chips {
-        trgmmux::add_trgmmux_from_sdk(data_dir, chip)?;
-    }
+    failures.run(&mut chips, "Handle TRGM MUX", |chip| {
+        trgmmux::add_trgmmux_from_sdk(data_dir, &families[&chip.name], chip)
+    });
+
+    stopwatch.section("Handle SoC features");
+    failures.run(&mut chips, "Handle SoC features", |chip| {
+        features::add_features_from_sdk(data_dir, &families[&chip.name], chip)
+    });
+
+    stopwatch.section("Checking peripheral addresses");
+    failures.run(&mut chips, "Checking peripheral addresses", |chip| {
+        addresses::check_addresses(data_dir, &families[&chip.name], chip)
+    });
+
+    stopwatch.section("Validating chip data");
+    validate::validate_chips(&chips, &registers)?;
 
     stopwatch.section("Writing chip data");
     for chip in &chips {
@@ -227,5 +235,137 @@ fn main() -> anyhow::Result<()> {
 
     stopwatch.stop();
 
-    Ok(())
+    failures.bail_if_any()
+}
+
+/// data/packages/*.yaml, `PA00: 35`, or `PE10: A1` for BGA packages, sorted by pad
+fn parse_pinout(content: &str) -> anyhow::Result<Vec<hpm_data_serde::chip::package::Pin>> {
+    let pinout: HashMap<String, serde_yaml::Value> = serde_yaml::from_str(content)?;
+
+    let mut pins = Vec::new();
+    for (pad, pin) in pinout {
+        let pin = match pin {
+            serde_yaml::Value::Number(n) => n.to_string(),
+            serde_yaml::Value::String(s) => s,
+            _ => anyhow::bail!("Invalid pin for {}: {:?}", pad, pin),
+        };
+        pins.push(hpm_data_serde::chip::package::Pin {
+            pin,
+            pad: hpm_data_serde::chip::core::peripheral::pin::Pin::parse(&pad)
+                .ok_or_else(|| anyhow::anyhow!("Invalid pad {}", pad))?,
+        });
+    }
+    pins.sort_by_key(|p| p.pad);
+    Ok(pins)
+}
+
+fn handle_includes(
+    meta_yaml_path: &Path,
+    chip: &mut hpm_data_serde::Chip,
+) -> anyhow::Result<family::Family> {
+    let Some(inc_path) = chip.include_sdk.take() else {
+        anyhow::bail!("No SDK layout for {}, missing include_sdk", chip.name);
+    };
+    let family_yaml_path = meta_yaml_path.parent().unwrap().join(&inc_path);
+    let family = family::Family::load(&family_yaml_path)?;
+    if chip.boot.is_none() {
+        chip.boot = family.boot.clone();
+    }
+
+    for package in &mut chip.packages {
+        if let Some(inc_path) = package.include_pinout.take() {
+            let pinout_yaml_path = meta_yaml_path.parent().unwrap().join(&inc_path);
+            let content = std::fs::read_to_string(&pinout_yaml_path).map_err(|e| {
+                anyhow::anyhow!("Failed to read file: {:?}: {}", &pinout_yaml_path, e)
+            })?;
+            package.pinout =
+                parse_pinout(&content).map_err(|e| anyhow::anyhow!("{}: {}", inc_path, e))?;
+        }
+    }
+    // no package features are generated for these
+    let missing: Vec<&str> = chip
+        .packages
+        .iter()
+        .filter(|p| p.pinout.is_empty())
+        .map(|p| p.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        println!("    {}: packages without pinout: {:?}", chip.name, missing);
+    }
+
+    for core in &mut chip.cores {
+        // append peripherals from includes
+        if let Some(inc_paths) = &mut core.include_peripherals.take() {
+            for inc_path in inc_paths {
+                let peripheral_yaml_path = meta_yaml_path.parent().unwrap().join(&inc_path);
+                let content = std::fs::read_to_string(&peripheral_yaml_path).map_err(|e| {
+                    anyhow::anyhow!("Failed to read file: {:?}: {}", &peripheral_yaml_path, e)
+                })?;
+                let peripherals: Vec<hpm_data_serde::chip::core::Peripheral> =
+                    serde_yaml::from_str(&content)?;
+                core.peripherals.extend(peripherals);
+            }
+        }
+
+        // generate dma channels
+        if let Some(gen) = &mut core.gen_dma_channels.take() {
+            anyhow::ensure!(
+                core.dma_channels.is_empty(),
+                "DMA channels already filled, cannot generate"
+            );
+
+            let &hdma_chs = gen
+                .get("HDMA")
+                .ok_or_else(|| anyhow::anyhow!("HDMA not found in gen_dma_channels"))?;
+            for ch in 0..hdma_chs {
+                core.dma_channels
+                    .push(hpm_data_serde::chip::core::DmaChannels {
+                        name: format!("HDMA_CH{}", ch),
+                        dma: "HDMA".to_string(),
+                        channel: ch as _,
+                        dmamux_channel: ch as _,
+                    });
+            }
+
+            if let Some(&xdma_ch) = gen.get("XDMA") {
+                for ch in 0..xdma_ch {
+                    core.dma_channels
+                        .push(hpm_data_serde::chip::core::DmaChannels {
+                            name: format!("XDMA_CH{}", ch),
+                            dma: "XDMA".to_string(),
+                            channel: ch as _,
+                            dmamux_channel: (hdma_chs + ch) as _, // xdma starts after hdma
+                        });
+                }
+            }
+        }
+    }
+
+    Ok(family)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the format of data/packages, the pin numbers are made up
+    #[test]
+    fn pinout() {
+        let pins = parse_pinout("PB01: 12\nPA31: 11\nPY00: A1\n").unwrap();
+        let pins: Vec<_> = pins
+            .iter()
+            .map(|p| (p.pad.to_string(), p.pin.as_str()))
+            .collect();
+        assert_eq!(
+            pins,
+            [
+                ("PA31".to_string(), "11"),
+                ("PB01".to_string(), "12"),
+                ("PY00".to_string(), "A1")
+            ]
+        );
+
+        assert!(parse_pinout("PA00: [1]\n").is_err());
+        assert!(parse_pinout("VDD: 1\n").is_err());
+    }
 }
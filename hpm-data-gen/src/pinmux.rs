@@ -1,7 +1,7 @@
 //! handle pinmux matching
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     path::Path,
 };
 
@@ -38,33 +38,110 @@ struct PinmuxRaw {
     data: Vec<Pin>,
 }
 
-// The following peripherals are supported now.
-const PERIPHERAL_LIST: &[&str] = &[
-    "GPTMR", "I2C", "SPI", "UART", "MCAN", "USB", "I2S", "PWM", "ACMP", "CAM", "FEMC", "PWM",
-    "QEI", "TRGM",
+/// Pinmux module names that differ from the peripheral names in chip data.
+const MODULE_RENAMES: &[(&str, &str)] = &[
+    ("ETH", "ENET"),
+    ("SDC", "SDXC"),
+    ("DIS", "LCDC"),
+    ("EWDG", "WDG"),
+    ("DAOL", "DAO"),
+    ("DAOR", "DAO"),
 ];
 
-fn normalize_func(module: &str, func: &str) -> String {
-    if module == "SYSCTL" {
-        func.replace("CLK_", "").replace("[", "").replace("]", "")
-    } else {
-        func.replace("[", "").replace("]", "")
+/// Modules handled elsewhere, GPIO functions are the IOC pins themselves.
+const IGNORED_MODULES: &[&str] = &["GPIO"];
+
+/// A pinmux function, before it is matched against the peripherals of a chip.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PinmuxAlt {
+    /// module name for reporting, e.g. ETH, ANALOGS.OPA
+    module: String,
+    /// peripheral name candidate, e.g. ENET0
+    peripheral: String,
+    signal: String,
+    pin: String,
+    /// None for analog functions, which are not selected by `FUNC_CTL`
+    alt: Option<u32>,
+}
+
+fn strip_brackets(func: &str) -> String {
+    func.replace("[", "").replace("]", "")
+}
+
+/// Split trailing instance number, "UART10" => ("UART", "10")
+fn split_instance(instance: &str) -> (&str, &str) {
+    let base = instance.trim_end_matches(|c: char| c.is_ascii_digit());
+    (base, &instance[base.len()..])
+}
+
+fn rename_instance(module: &str, instance: &str) -> String {
+    match MODULE_RENAMES.iter().find(|(from, _)| *from == module) {
+        Some((from, to)) => match instance.strip_prefix(from) {
+            Some(rest) => format!("{}{}", to, rest),
+            None => instance.to_string(),
+        },
+        None => instance.to_string(),
+    }
+}
+
+/// Per-module signal normalization rules, returns (peripheral, signal)
+fn normalize_alt(module: &str, instance: &str, func: &str) -> (String, String) {
+    let peripheral = rename_instance(module, instance);
+    match module {
+        // CLK_OBS[0] => OBS0
+        "SYSCTL" => (peripheral, strip_brackets(&func.replace("CLK_", ""))),
+        // DAOL.P => LP, DAOR.N => RN, same naming as HPM6800's DAO
+        "DAOL" => (peripheral, format!("L{}", func)),
+        "DAOR" => (peripheral, format!("R{}", func)),
+        // SEI0, SEI1 are channels of the single SEI peripheral: SEI0.TX => SEI.CH0_TX
+        "SEI" => {
+            let (base, ch) = split_instance(instance);
+            (
+                base.to_string(),
+                format!("CH{}_{}", ch, strip_brackets(func)),
+            )
+        }
+        _ => (peripheral, strip_brackets(func)),
     }
 }
 
 fn get_pmic_periph_and_func(func: &str) -> Option<(String, String)> {
-    // PUART, PTMR,
+    // PUART, PTMR, PWDG, VAD
     if let Some((periph, f)) = func.split_once(".") {
-        match periph {
-            "PUART" => Some(("PUART".to_string(), f.to_string())),
-            "PTMR" => Some(("PTMR".to_string(), f.replace("[", "").replace("]", ""))),
-            _ => None,
-        }
+        let periph = match periph {
+            // HPM6880 and HPM6E80 use PURT, HPM6284 uses plain UART
+            "PUART" | "PURT" | "UART" => "PUART",
+            "PTMR" => "PTMR",
+            "PWDG" | "PEWDG" | "WDOG" => "PWDG",
+            "VAD" => "VAD",
+            other => other,
+        };
+        Some((periph.to_string(), strip_brackets(f)))
     } else {
         None
     }
 }
 
+fn get_batt_periph_and_func(func: &str) -> (String, String) {
+    let func = func.strip_prefix("BATT.").unwrap_or(func);
+    match func {
+        "PBUTN" | "WBUTN" | "PLED" | "WLED" => ("BUTN".to_string(), func.to_string()),
+        // TAMP[00], TAMPER[00], TAMP.PZ[00] => TAMP0
+        f if f.starts_with("TAMP") => {
+            let n = f
+                .trim_end_matches(']')
+                .rsplit('[')
+                .next()
+                .unwrap()
+                .parse::<u32>()
+                .unwrap();
+            ("TAMP".to_string(), format!("TAMP{}", n))
+        }
+        // RESETN, PWR_ON, HIBERNATE, STANDBY, no peripheral to attach to
+        f => ("BATT".to_string(), f.to_string()),
+    }
+}
+
 fn convert_acmp_func(instance: &str, func: &str) -> String {
     if func.contains("_") && func.starts_with("CMP") {
         func.to_string()
@@ -77,100 +154,181 @@ fn convert_acmp_func(instance: &str, func: &str) -> String {
     }
 }
 
-pub fn handle_pinmux<P: AsRef<Path>>(
-    path: P,
-    chip: &mut hpm_data_serde::Chip,
-) -> anyhow::Result<()> {
-    let data = std::fs::read_to_string(&path)?;
-    let pinmux: PinmuxRaw = serde_json::from_str(&data)?;
-
-    let pins = pinmux.data;
+fn analog_alt(alt_def: &AltDef) -> (String, String, String) {
+    let periph = alt_def.instance.to_string();
+    // ADC0, ADC1, ADC2, ADC3
+    if alt_def.instance.starts_with("ADC") {
+        // Conversions:
+        // - VINP => INP, VINN => INN (HPM6750's ADC12 (ADC0, ADC1, ADC2) supports differential input: VINP, VINN)
+        // - INA0 => IN0
+        // - IN01 => IN1
+        let mut signal_name = alt_def
+            .func
+            .replace("VINP", "INP")
+            .replace("VINN", "INN")
+            .replace("INA", "IN")
+            .to_string();
+        if signal_name.len() == 4 && signal_name.starts_with("0") {
+            signal_name = signal_name.replace("IN0", "IN");
+        }
+        ("ANALOGS.ADC".to_string(), periph, signal_name)
+    } else if alt_def.instance.starts_with("DAC") {
+        ("ANALOGS.DAC".to_string(), periph, alt_def.func.to_string()) // OUT
+    } else if alt_def.instance.starts_with("ACMP") {
+        let signal_name = convert_acmp_func(&periph, &alt_def.func);
+        ("ANALOGS.ACMP".to_string(), periph, signal_name)
+    } else if let Some(n) = alt_def.instance.strip_prefix("OPA") {
+        // OPA0 => OPAMP0
+        (
+            "ANALOGS.OPA".to_string(),
+            format!("OPAMP{}", n),
+            alt_def.func.to_string(),
+        )
+    } else {
+        // LVDS, MIPI
+        let (base, _) = split_instance(&alt_def.instance);
+        (
+            format!("ANALOGS.{}", base),
+            periph,
+            alt_def.func.to_string(),
+        )
+    }
+}
 
-    // peripheral_name, signal_name, pin_name, alt_num
-    let mut pinmux_alt_defs: HashSet<(String, String, String, u32)> = HashSet::new();
+fn collect_pinmux_alts(pins: &[Pin]) -> HashSet<PinmuxAlt> {
+    let mut pinmux_alts = HashSet::new();
 
     for pin in pins {
         for (_alt_name, alt_def) in &pin.alts {
-            if PERIPHERAL_LIST.contains(&&*alt_def.module) {
-                let signal_name = normalize_func(&alt_def.module, &alt_def.func);
-                pinmux_alt_defs.insert((
-                    alt_def.instance.clone(),
-                    signal_name,
-                    pin.name.clone(),
-                    alt_def.alt_num(),
-                ));
+            if IGNORED_MODULES.contains(&&*alt_def.module) {
+                continue;
             }
+            let (peripheral, signal) =
+                normalize_alt(&alt_def.module, &alt_def.instance, &alt_def.func);
+            pinmux_alts.insert(PinmuxAlt {
+                module: alt_def.module.clone(),
+                peripheral,
+                signal,
+                pin: pin.name.clone(),
+                alt: Some(alt_def.alt_num()),
+            });
         }
 
-        //  Analog peripherals
-        if pin.specials.contains_key("ANALOGS") {
-            for (_name, alt_def) in &pin.specials["ANALOGS"] {
-                // ADC0, ADC1, ADC2, ADC3
-                if alt_def.instance.starts_with("ADC") {
-                    let periph = alt_def.instance.to_string();
-                    // Conversions:
-                    // - VINP => INP, VINN => INN (HPM6750's ADC12 (ADC0, ADC1, ADC2) supports differential input: VINP, VINN)
-                    // - INA0 => IN0
-                    // - IN01 => IN1
-                    let mut signal_name = alt_def
-                        .func
-                        .replace("VINP", "INP")
-                        .replace("VINN", "INN")
-                        .replace("INA", "IN")
-                        .to_string();
-                    if signal_name.len() == 4 && signal_name.starts_with("0") {
-                        signal_name = signal_name.replace("IN0", "IN");
-                    }
-                    pinmux_alt_defs.insert((periph, signal_name, pin.name.clone(), 0));
-                } else if alt_def.instance.starts_with("DAC") {
-                    let periph = alt_def.instance.to_string();
-                    let signal_name = alt_def.func.to_string(); // OUT
-
-                    pinmux_alt_defs.insert((periph, signal_name, pin.name.clone(), 0));
-                } else if alt_def.instance.starts_with("ACMP") {
-                    let periph = alt_def.instance.to_string();
-                    let signal_name = convert_acmp_func(&periph, &alt_def.func);
-
-                    pinmux_alt_defs.insert((periph, signal_name, pin.name.clone(), 0));
-                }
-            }
-        }
-        // power domain peripherals
-        if pin.specials.contains_key("PMIC") {
-            for (_alt_name, alt_def) in &pin.specials["PMIC"] {
-                if let Some((periph, signal_name)) = get_pmic_periph_and_func(&alt_def.func) {
-                    pinmux_alt_defs.insert((
-                        periph,
-                        signal_name,
-                        pin.name.clone(),
-                        alt_def.alt_num(),
-                    ));
+        for (special, alt_defs) in &pin.specials {
+            for (_name, alt_def) in alt_defs {
+                // PGPIO, BGPIO, GPIO
+                if alt_def.func.contains("GPIO") {
+                    continue;
                 }
+
+                let (module, peripheral, signal, alt) = match &**special {
+                    // Analog peripherals, no alt function
+                    "ANALOGS" => {
+                        let (module, peripheral, signal) = analog_alt(alt_def);
+                        (module, peripheral, signal, None)
+                    }
+                    // power domain peripherals
+                    "PMIC" => {
+                        let Some((peripheral, signal)) = get_pmic_periph_and_func(&alt_def.func)
+                        else {
+                            continue;
+                        };
+                        (
+                            format!("PMIC.{}", peripheral),
+                            peripheral,
+                            signal,
+                            Some(alt_def.alt_num()),
+                        )
+                    }
+                    // battery domain peripherals
+                    "BATT" => {
+                        let (peripheral, signal) = get_batt_periph_and_func(&alt_def.func);
+                        (
+                            format!("BATT.{}", peripheral),
+                            peripheral,
+                            signal,
+                            Some(alt_def.alt_num()),
+                        )
+                    }
+                    _ => (
+                        special.clone(),
+                        alt_def.instance.clone(),
+                        strip_brackets(&alt_def.func),
+                        Some(alt_def.alt_num()),
+                    ),
+                };
+                pinmux_alts.insert(PinmuxAlt {
+                    module,
+                    peripheral,
+                    signal,
+                    pin: pin.name.clone(),
+                    alt,
+                });
             }
         }
     }
 
-    // println!("Found {:#?} pinmux alt defs", pinmux_alt_defs);
-
-    let mut periph_pins: HashMap<String, Vec<(String, String, u32)>> = HashMap::new();
+    pinmux_alts
+}
 
-    for (peripheral_name, signal_name, pin_name, alt_num) in pinmux_alt_defs {
-        periph_pins
-            .entry(peripheral_name)
-            .or_insert_with(Vec::new)
-            .push((signal_name, pin_name, alt_num));
+/// Match a pinmux instance to a peripheral of the chip.
+///
+/// Single instance peripherals are named without index in chip data, e.g. PDM0 => PDM.
+fn resolve_peripheral<'a>(peripheral_names: &HashSet<&'a str>, name: &str) -> Option<&'a str> {
+    if let Some(&name) = peripheral_names.get(name) {
+        return Some(name);
+    }
+    match split_instance(name) {
+        (base, "0") => peripheral_names.get(base).copied(),
+        _ => None,
     }
+}
+
+pub fn handle_pinmux<P: AsRef<Path>>(
+    path: P,
+    chip: &mut hpm_data_serde::Chip,
+) -> anyhow::Result<()> {
+    let data = std::fs::read_to_string(&path)?;
+    let pinmux: PinmuxRaw = serde_json::from_str(&data)?;
+
+    let pinmux_alts = collect_pinmux_alts(&pinmux.data);
+
+    // println!("Found {:#?} pinmux alt defs", pinmux_alts);
 
-    // fill pins
     for core in &mut chip.cores {
-        for peripheral in &mut core.peripherals {
-            if !peripheral.pins.is_empty() {
-                println!(
-                    "Skipping peripheral {} as it already has pins",
-                    peripheral.name
-                );
-                continue;
+        let peripheral_names: HashSet<&str> =
+            core.peripherals.iter().map(|p| p.name.as_str()).collect();
+
+        let mut periph_pins: HashMap<String, Vec<(String, String, Option<u32>)>> = HashMap::new();
+        // instances missing on this chip, e.g. UART8 on HPM6830 while the pinmux is HPM6880's
+        let mut unmapped_instances: BTreeSet<&str> = BTreeSet::new();
+
+        for alt in &pinmux_alts {
+            if let Some(peripheral_name) = resolve_peripheral(&peripheral_names, &alt.peripheral) {
+                periph_pins
+                    .entry(peripheral_name.to_string())
+                    .or_insert_with(Vec::new)
+                    .push((alt.signal.clone(), alt.pin.clone(), alt.alt));
+            } else {
+                unmapped_instances.insert(&alt.peripheral);
             }
+        }
+        if !unmapped_instances.is_empty() {
+            println!(
+                "    {}: unmapped pinmux instances: {:?}",
+                chip.name, unmapped_instances
+            );
+        }
+
+        // fill pins
+        for peripheral in &mut core.peripherals {
+            // pinmux is the only source of pins, one from chip data would be silently mixed in
+            anyhow::ensure!(
+                peripheral.pins.is_empty(),
+                "{}: peripheral {} has pins in chip data",
+                chip.name,
+                peripheral.name
+            );
 
             peripheral.pins = periph_pins
                 .get(&peripheral.name)
@@ -183,7 +341,7 @@ pub fn handle_pinmux<P: AsRef<Path>>(
                                     pin_name,
                                 )
                                 .unwrap(),
-                                alt: Some(*alt_num as _),
+                                alt: alt_num.map(|alt| alt as _),
                             }
                         })
                         .collect()
@@ -1,34 +1,18 @@
 //! parse sysctl registers from sdk_code
 
-use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
-};
+use std::{collections::HashMap, path::Path};
+
+use crate::family::Family;
 
 pub fn add_ioc_pins_from_sdk<P: AsRef<Path>>(
     data_dir: P,
+    family: &Family,
     chip: &mut hpm_data_serde::Chip,
 ) -> anyhow::Result<()> {
-    let sdk_path = std::env::var("HPM_SDK_BASE")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| data_dir.as_ref().parent().unwrap().join("hpm_sdk"));
-
-    let chip_name = &chip.name;
-
-    let header_file = match chip_name {
-        n if n.starts_with("HPM53") => sdk_path.join("soc/HPM5300/ip/hpm_ioc_regs.h"),
-        n if n.starts_with("HPM62") => sdk_path.join("soc/HPM6200/ip/hpm_ioc_regs.h"),
-        n if n.starts_with("HPM63") => sdk_path.join("soc/HPM6300/ip/hpm_ioc_regs.h"),
-        n if n.starts_with("HPM67") || n.starts_with("HPM64") => {
-            sdk_path.join("soc/HPM6700/ip/hpm_ioc_regs.h")
-        }
-        n if n.starts_with("HPM68") => sdk_path.join("soc/HPM6800/ip/hpm_ioc_regs.h"),
-        n if n.starts_with("HPM6E") => sdk_path.join("soc/HPM6E00/ip/hpm_ioc_regs.h"),
-        _ => anyhow::bail!("Unknown chip: {}", chip_name),
-    };
+    let header_file = family.header(&data_dir, &family.headers.ioc);
 
     let content = std::fs::read_to_string(&header_file)
-        .expect(format!("Failed to read file: {:?}", &header_file).as_str());
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", &header_file, e))?;
 
     // #define IOC_PAD_PA00 (0UL)
     let ioc_pin_pattern =
@@ -52,12 +36,12 @@ pub fn add_ioc_pins_from_sdk<P: AsRef<Path>>(
         .collect();
 
     // fix wrong pins
-    if chip_name.starts_with("HPM53") {
-        pins = pins
-            .into_iter()
-            .filter(|p| !p.name.starts_with("PX"))
-            .collect();
-    }
+    pins.retain(|p| {
+        !family
+            .exclude_pads
+            .iter()
+            .any(|prefix| p.name.starts_with(prefix))
+    });
 
     pins.sort_by_key(|p| p.index);
 
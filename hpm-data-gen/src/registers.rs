@@ -6,11 +6,14 @@ use chiptool::validate;
 
 pub struct Registers {
     pub registers: HashMap<String, IR>,
+    /// the YAML as written, for the keys chiptool drops
+    raw: HashMap<String, serde_yaml::Value>,
 }
 
 impl Registers {
     pub fn parse() -> Result<Self, anyhow::Error> {
         let mut registers = HashMap::new();
+        let mut raw = HashMap::new();
 
         for f in glob::glob("data/registers/*")? {
             let f = f?;
@@ -21,7 +24,8 @@ impl Registers {
                 .strip_suffix(".yaml")
                 .unwrap()
                 .to_string();
-            let ir: IR = serde_yaml::from_str(&std::fs::read_to_string(&f)?)
+            let content = std::fs::read_to_string(&f)?;
+            let ir: IR = serde_yaml::from_str(&content)
                 .map_err(|e| anyhow!("failed to parse {f:?}: {e:?}"))?;
 
             // validate yaml file
@@ -44,19 +48,63 @@ impl Registers {
                 return Err(anyhow!(format!("\n{ff}:\n{err_string}")));
             }
 
+            raw.insert(ff.clone(), serde_yaml::from_str(&content)?);
             registers.insert(ff, ir);
         }
 
-        Ok(Self { registers })
+        Ok(Self { registers, raw })
     }
 
     pub fn write(&self) -> Result<(), anyhow::Error> {
         std::fs::create_dir_all("build/data/registers")?;
 
         for (name, ir) in &self.registers {
-            let dump = serde_json::to_string_pretty(ir)?;
+            let mut json = serde_json::to_value(ir)?;
+            copy_extra_keys(&self.raw[name], &mut json);
+            let dump = serde_json::to_string_pretty(&json)?;
             std::fs::write(format!("build/data/registers/{name}.json"), dump)?;
         }
         Ok(())
     }
 }
+
+/// chiptool drops these keys, they are copied from the YAML
+const EXTRA_KEYS: &[(&str, &str, &str)] = &[
+    ("block/", "items", "reset_value"),
+    ("fieldset/", "fields", "modified_write_values"),
+    ("fieldset/", "fields", "read_action"),
+];
+
+/// Items and fields keep their order from YAML to JSON
+fn copy_extra_keys(yaml: &serde_yaml::Value, json: &mut serde_json::Value) {
+    let Some(entries) = yaml.as_mapping() else {
+        return;
+    };
+    for (key, entry) in entries {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        for &(prefix, list, extra) in EXTRA_KEYS {
+            if !key.starts_with(prefix) {
+                continue;
+            }
+            let Some(elements) = entry.get(list).and_then(|l| l.as_sequence()) else {
+                continue;
+            };
+            for (i, element) in elements.iter().enumerate() {
+                let Some(value) = element.get(extra) else {
+                    continue;
+                };
+                if let Some(serde_json::Value::Object(element)) = json
+                    .get_mut(key)
+                    .and_then(|e| e.get_mut(list))
+                    .and_then(|l| l.get_mut(i))
+                {
+                    if let Ok(value) = serde_json::to_value(value) {
+                        element.insert(extra.to_string(), value);
+                    }
+                }
+            }
+        }
+    }
+}
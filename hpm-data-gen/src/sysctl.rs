@@ -3,66 +3,46 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::LazyLock,
+    sync::{LazyLock, Mutex},
 };
 
+use crate::family::{self, Family};
+
 // Defined in hpm_sysctl_drv.h
 // This is the relation between the resource and the group link number.
 // The conversion logic is `sysctl_enable_group_resource`.
 const SYSCTL_RESOURCE_LINKABLE_START: u32 = 256;
 
-static HPM_SDK_BASE: LazyLock<PathBuf> = LazyLock::new(|| {
-    std::env::var("HPM_SDK_BASE")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| std::env::current_dir().unwrap().join("./hpm_sdk"))
-});
-
-static HPM5300_SYSCTL: LazyLock<SysctlInfo> = LazyLock::new(|| {
-    load_sysctl_info_from_header(
-        "HPM5300",
-        HPM_SDK_BASE.join("soc/HPM5300/ip/hpm_sysctl_regs.h"),
-    )
-    .expect("Failed to load HPM5300 sysctl info")
-});
-static HPM6200_SYSCTL: LazyLock<SysctlInfo> = LazyLock::new(|| {
-    load_sysctl_info_from_header(
-        "HPM6200",
-        HPM_SDK_BASE.join("soc/HPM6200/ip/hpm_sysctl_regs.h"),
-    )
-    .expect("Failed to load HPM6200 sysctl info")
-});
-static HPM6300_SYSCTL: LazyLock<SysctlInfo> = LazyLock::new(|| {
-    load_sysctl_info_from_header(
-        "HPM6300",
-        HPM_SDK_BASE.join("soc/HPM6300/ip/hpm_sysctl_regs.h"),
-    )
-    .expect("Failed to load HPM6300 sysctl info")
-});
-static HPM6700_SYSCTL: LazyLock<SysctlInfo> = LazyLock::new(|| {
-    load_sysctl_info_from_header(
-        "HPM6700",
-        HPM_SDK_BASE.join("soc/HPM6700/ip/hpm_sysctl_regs.h"),
-    )
-    .expect("Failed to load HPM6700 sysctl info")
-});
-static HPM6800_SYSCTL: LazyLock<SysctlInfo> = LazyLock::new(|| {
-    load_sysctl_info_from_header(
-        "HPM6800",
-        HPM_SDK_BASE.join("soc/HPM6800/ip/hpm_sysctl_regs.h"),
-    )
-    .expect("Failed to load HPM6800 sysctl info")
-});
-static HPM6E00_SYSCTL: LazyLock<SysctlInfo> = LazyLock::new(|| {
-    load_sysctl_info_from_header(
-        "HPM6E00",
-        HPM_SDK_BASE.join("soc/HPM6E00/ip/hpm_sysctl_regs.h"),
-    )
-    .expect("Failed to load HPM6E00 sysctl info")
-});
+// Sysctl headers are shared by all chips of a family, parse once
+static SYSCTL_INFOS: LazyLock<Mutex<HashMap<PathBuf, SysctlInfo>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// peripheral name prefix => SDK resource name prefix
+const SDK_NAMES: &[(&str, &str)] = &[
+    ("MCHTMR", "MCT"),
+    ("GPTMR", "TMR"),
+    ("OPAMP", "OPA"),
+    ("UART", "URT"),
+    ("KEYM", "KMAN"),
+    ("CRC", "CRC0"),
+    ("FFA", "FFA0"),
+    ("MBX0A", "MBX0"),
+    ("MBX0B", "MBX0"),
+    ("MBX1A", "MBX1"),
+    ("MBX1B", "MBX1"),
+    ("SDP", "SDP0"),
+    ("NTMR", "NTM"),
+    ("SDXC", "SDC"),
+    ("PPI", "PPI0"),
+    ("SEI", "SEI0"),
+    ("RNG", "RNG0"),
+    ("TSW", "TSW0"),
+    ("PLB", "PLB0"),
+];
 
 #[derive(Debug, Clone)]
 pub struct SysctlInfo {
-    pub chip_family: String,
+    pub config: family::Sysctl,
     // for resources register
     pub resources: HashMap<String, u32>,
     // For clocks register
@@ -73,55 +53,33 @@ impl SysctlInfo {
     // SDK name is 4 char name, like MCT0, CAN0, TMR0, etc.
     // Here, we need to convert it to the peripheral name used in hpm-data.
     fn peripheral_name_to_sdk_name(&self, name: &str) -> String {
-        // convert peripheral name to SDK name
-        let mut trans: HashMap<_, _> = [
-            ("MCHTMR", "MCT"), // how to handle dual-core?
-            ("GPTMR", "TMR"),
-            ("OPAMP", "OPA"),
-            ("UART", "URT"),
-            ("KEYM", "KMAN"),
-            ("CRC", "CRC0"),
-            ("FFA", "FFA0"),
-            ("MBX0A", "MBX0"),
-            ("MBX0B", "MBX0"),
-            ("MBX1A", "MBX1"),
-            ("MBX1B", "MBX1"),
-            ("SDP", "SDP0"),
-            ("NTMR", "NTM"),
-            ("SDXC", "SDC"),
-            ("PPI", "PPI0"),
-            ("SEI", "SEI0"),
-            ("RNG", "RNG0"),
-            ("TSW", "TSW0"),
-            ("PLB", "PLB0"),
-        ]
-        .into_iter()
-        .collect();
-        if self.chip_family.starts_with("HPM53")
-            || self.chip_family.starts_with("HPM62")
-            || self.chip_family.starts_with("HPM68")
-            || self.chip_family.starts_with("HPM6E")
+        // family names replace the common ones of the same prefix
+        let mut trans: Vec<(&str, &str)> = SDK_NAMES
+            .iter()
+            .filter(|(k, _)| !self.config.names.contains_key(*k))
+            .copied()
+            .collect();
+        trans.extend(
+            self.config
+                .names
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        );
+
+        // the longest matching prefix, MBX0A before MBX0
+        match trans
+            .iter()
+            .filter(|(k, _)| name.starts_with(k))
+            .max_by_key(|(k, _)| k.len())
         {
-            trans.insert("MCAN", "CAN");
-        }
-        if self.chip_family.starts_with("HPM63") {
-            trans.insert("HDMA", "DMA0");
-            trans.insert("XDMA", "DMA1");
+            Some((k, v)) => format!("{}{}", v, &name[k.len()..]),
+            None => name.to_string(),
         }
-
-        let mut pname = name.to_string();
-        for (k, v) in &trans {
-            if pname.starts_with(*k) {
-                pname = pname.replace(k, v);
-                break;
-            }
-        }
-
-        pname
     }
+
     fn get_resource(&self, name: &str) -> Option<u32> {
         // applies to HPM6700 and HPM6400
-        if self.chip_family == "HPM6700" {
+        if self.config.plain_names {
             return self.resources.get(name).copied();
         }
 
@@ -143,7 +101,7 @@ impl SysctlInfo {
 
     fn get_clock(&self, name: &str) -> Option<u32> {
         // applies to HPM6700 and HPM6400
-        if self.chip_family == "HPM6700" {
+        if self.config.plain_names {
             return self.clocks.get(name).copied();
         }
 
@@ -160,11 +118,16 @@ impl SysctlInfo {
 }
 
 fn load_sysctl_info_from_header<P: AsRef<Path>>(
-    chip_family: &str,
+    config: &family::Sysctl,
     header_path: P,
 ) -> anyhow::Result<SysctlInfo> {
-    let content = std::fs::read_to_string(&header_path)
-        .expect(format!("Failed to read file: {:?}", header_path.as_ref().display()).as_str());
+    let content = std::fs::read_to_string(&header_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read file: {:?}: {}",
+            header_path.as_ref().display(),
+            e
+        )
+    })?;
 
     // #define SYSCTL_RESOURCE_MCT0 (258UL)
     // => MCT0: 258
@@ -196,48 +159,59 @@ fn load_sysctl_info_from_header<P: AsRef<Path>>(
         .collect();
 
     // Fix: `#define SYSCTL_CLOCK_CLK_TOP_MCHTMR (3UL)`
-    if chip_family.starts_with("HPM67") || chip_family.starts_with("HPM64") {
-        if let Some(i) = clocks.remove("MCHTMR") {
-            clocks.insert("MCHTMR1".to_string(), i);
+    for (from, to) in &config.clock_renames {
+        if let Some(i) = clocks.remove(from) {
+            clocks.insert(to.clone(), i);
         }
     }
     // println!("resources: {:#?}", resources);
     // println!("clocks: {:#?}", clocks);
 
     println!(
-        "    Load SYSCTL for {}: {} resources, {} clocks",
-        chip_family,
+        "    Load SYSCTL from {}: {} resources, {} clocks",
+        header_path.as_ref().display(),
         resources.len(),
         clocks.len()
     );
 
     Ok(SysctlInfo {
-        chip_family: chip_family.to_string(),
+        config: config.clone(),
         resources,
         clocks,
     })
 }
 
 pub fn add_sysctl_from_sdk<P: AsRef<Path>>(
-    _data_dir: P,
+    data_dir: P,
+    family: &Family,
     chip: &mut hpm_data_serde::Chip,
 ) -> anyhow::Result<()> {
-    let chip_name = &chip.name;
-
-    let info = match chip_name {
-        n if n.starts_with("HPM53") => &HPM5300_SYSCTL,
-        n if n.starts_with("HPM62") => &HPM6200_SYSCTL,
-        n if n.starts_with("HPM63") => &HPM6300_SYSCTL,
-        n if n.starts_with("HPM67") || n.starts_with("HPM64") => &HPM6700_SYSCTL,
-        n if n.starts_with("HPM68") => &HPM6800_SYSCTL,
-        n if n.starts_with("HPM6E") => &HPM6E00_SYSCTL,
-        _ => anyhow::bail!("Unknown chip: {}", chip_name),
-    };
-
-    // build Systick info
-    // only one core variant
-    let core = &mut chip.cores[0];
+    let header_path = family.header(&data_dir, &family.headers.sysctl);
+
+    let mut infos = SYSCTL_INFOS.lock().unwrap();
+    if !infos.contains_key(&header_path) {
+        let info = load_sysctl_info_from_header(&family.sysctl, &header_path)?;
+        infos.insert(header_path.clone(), info);
+    }
+    let info = &infos[&header_path];
+
+    for core in &mut chip.cores {
+        add_sysctl_to_core(info, core);
+    }
 
+    Ok(())
+}
+
+// Every core sees its own MCHTMR at the same address, its resource and clock follow the hart,
+// like MCT0/MCT1 or MCHTMR0/MCHTMR1.
+fn sysctl_name(periph: &str, hart_id: u8) -> String {
+    match periph {
+        "MCHTMR" => format!("MCHTMR{}", hart_id),
+        _ => periph.to_string(),
+    }
+}
+
+fn add_sysctl_to_core(info: &SysctlInfo, core: &mut hpm_data_serde::chip::Core) {
     core.resources = info
         .resources
         .iter()
@@ -263,15 +237,16 @@ pub fn add_sysctl_from_sdk<P: AsRef<Path>>(
             continue; // already set
         }
 
-        let res = info.get_resource(&periph.name);
+        let name = sysctl_name(&periph.name, core.hart_id);
+        let res = info.get_resource(&name);
 
         let Some(res_no) = res else {
             continue; // skip peripherals without sysctl
         };
 
-        let clock = info.get_clock(&periph.name);
+        let clock = info.get_clock(&name);
 
-        let clock_top_res_no = info.get_clock_top_resource(&periph.name);
+        let clock_top_res_no = info.get_clock_top_resource(&name);
 
         if res_no < SYSCTL_RESOURCE_LINKABLE_START {
             continue; // skip non-linkable resources
@@ -290,6 +265,26 @@ pub fn add_sysctl_from_sdk<P: AsRef<Path>>(
 
         periph.sysctl = Some(sysclk);
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sdk_name_longest_prefix() {
+        let info = SysctlInfo {
+            config: family::Sysctl {
+                names: [("MCAN".to_string(), "CAN".to_string())].into(),
+                ..Default::default()
+            },
+            resources: HashMap::new(),
+            clocks: HashMap::new(),
+        };
+        assert_eq!(info.peripheral_name_to_sdk_name("MBX0A"), "MBX0");
+        assert_eq!(info.peripheral_name_to_sdk_name("MCHTMR0"), "MCT0");
+        assert_eq!(info.peripheral_name_to_sdk_name("MCAN3"), "CAN3");
+        assert_eq!(info.peripheral_name_to_sdk_name("UART12"), "URT12");
+        assert_eq!(info.peripheral_name_to_sdk_name("I2C0"), "I2C0");
+    }
 }
@@ -1,18 +1,14 @@
 //! parse trgm mux defines from sdk_code
 
-use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
-};
+use std::{collections::HashMap, path::Path};
+
+use crate::family::Family;
 
 pub fn add_trgmmux_from_sdk<P: AsRef<Path>>(
     data_dir: P,
+    family: &Family,
     chip: &mut hpm_data_serde::Chip,
 ) -> anyhow::Result<()> {
-    let sdk_path = std::env::var("HPM_SDK_BASE")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| data_dir.as_ref().parent().unwrap().join("hpm_sdk"));
-
     let chip_name = &chip.name;
 
     if chip.cores[0]
@@ -24,19 +20,13 @@ pub fn add_trgmmux_from_sdk<P: AsRef<Path>>(
         return Ok(()); // No TRGM peripheral
     }
 
-    let header_file = match chip_name {
-        n if n.starts_with("HPM53") => sdk_path.join("soc/HPM5300/HPM5361/hpm_trgmmux_src.h"),
-        n if n.starts_with("HPM62") => sdk_path.join("soc/HPM6200/HPM6280/hpm_trgmmux_src.h"),
-        n if n.starts_with("HPM63") => sdk_path.join("soc/HPM6300/HPM6360/hpm_trgmmux_src.h"),
-        n if n.starts_with("HPM67") || n.starts_with("HPM64") => {
-            sdk_path.join("soc/HPM6700/HPM6750/hpm_trgmmux_src.h")
-        }
-        n if n.starts_with("HPM6E") => sdk_path.join("soc/HPM6E00/HPM6E80/hpm_trgmmux_src.h"),
-        _ => anyhow::bail!("Unknown chip: {}", chip_name),
+    let Some(header) = &family.headers.trgmmux else {
+        anyhow::bail!("No trgmmux header declared for {}", chip_name);
     };
+    let header_file = family.header(&data_dir, header);
 
     let content = std::fs::read_to_string(&header_file)
-        .expect(format!("Failed to read file: {:?}", &header_file).as_str());
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {:?}: {}", &header_file, e))?;
 
     // #define HPM_TRGM0_FILTER_SRC_PWM0_IN0                      (0x0UL)
     let resource_pattern =
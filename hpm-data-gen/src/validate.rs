@@ -0,0 +1,384 @@
+//! cross-reference checks on chip data, run before writing build/data/chips
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use chiptool::ir::{Array, BlockItemInner, IR};
+
+use crate::registers::Registers;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Check {
+    Interrupt,
+    Dma,
+    Address,
+    Sysctl,
+    Pin,
+    Registers,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Check::Interrupt => "interrupt",
+            Check::Dma => "dma",
+            Check::Address => "address",
+            Check::Sysctl => "sysctl",
+            Check::Pin => "pin",
+            Check::Registers => "registers",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub core: String,
+    pub check: Check,
+    pub message: String,
+}
+
+/// All issues found, grouped by chip name
+#[derive(Debug, Default)]
+pub struct Report {
+    pub issues: BTreeMap<String, Vec<Issue>>,
+}
+
+impl Report {
+    fn push(&mut self, chip: &str, core: &str, check: Check, message: String) {
+        self.issues
+            .entry(chip.to_string())
+            .or_default()
+            .push(Issue {
+                core: core.to_string(),
+                check,
+                message,
+            });
+    }
+
+    pub fn len(&self) -> usize {
+        self.issues.values().map(|v| v.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (chip, issues) in &self.issues {
+            writeln!(f, "{}: {} issue(s)", chip, issues.len())?;
+            let mut issues = issues.clone();
+            issues.sort_by(|a, b| (&a.core, a.check).cmp(&(&b.core, b.check)));
+            for issue in &issues {
+                writeln!(f, "    [{}] {}: {}", issue.check, issue.core, issue.message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate all chips, bail with the full report when anything is inconsistent.
+pub fn validate_chips(chips: &[hpm_data_serde::Chip], registers: &Registers) -> anyhow::Result<()> {
+    let mut report = Report::default();
+
+    for chip in chips {
+        validate_chip(chip, registers, &mut report);
+    }
+
+    if !report.is_empty() {
+        eprint!("{}", report);
+        anyhow::bail!(
+            "chip data validation failed: {} issue(s) in {} chip(s)",
+            report.len(),
+            report.issues.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn validate_chip(chip: &hpm_data_serde::Chip, registers: &Registers, report: &mut Report) {
+    for core in &chip.cores {
+        let mut push = |check, message| report.push(&chip.name, &core.name, check, message);
+
+        // interrupts
+        let mut interrupt_names = HashSet::new();
+        let mut interrupt_numbers = HashMap::new();
+        for irq in &core.interrupts {
+            if !interrupt_names.insert(irq.name.as_str()) {
+                push(
+                    Check::Interrupt,
+                    format!("duplicate interrupt {}", irq.name),
+                );
+            }
+            if let Some(other) = interrupt_numbers.insert(irq.number, irq.name.as_str()) {
+                push(
+                    Check::Interrupt,
+                    format!(
+                        "interrupt number {} used by both {} and {}",
+                        irq.number, other, irq.name
+                    ),
+                );
+            }
+        }
+        for periph in &core.peripherals {
+            for irq in periph.interrupts.iter().flatten() {
+                if !interrupt_names.contains(irq.interrupt.as_str()) {
+                    push(
+                        Check::Interrupt,
+                        format!(
+                            "{}.{} refers to unknown interrupt {}",
+                            periph.name, irq.signal, irq.interrupt
+                        ),
+                    );
+                }
+            }
+        }
+
+        // DMA channels and DMAMUX requests
+        let mut channel_names = HashSet::new();
+        let mut dmamux_channels = HashMap::new();
+        for ch in &core.dma_channels {
+            if !channel_names.insert(ch.name.as_str()) {
+                push(Check::Dma, format!("duplicate DMA channel {}", ch.name));
+            }
+            if let Some(other) = dmamux_channels.insert(ch.dmamux_channel, ch.name.as_str()) {
+                push(
+                    Check::Dma,
+                    format!(
+                        "DMAMUX channel {} used by both {} and {}",
+                        ch.dmamux_channel, other, ch.name
+                    ),
+                );
+            }
+            if !core.peripherals.iter().any(|p| p.name == ch.dma) {
+                push(
+                    Check::Dma,
+                    format!("DMA channel {} refers to unknown {}", ch.name, ch.dma),
+                );
+            }
+        }
+        let mut requests: HashMap<(&str, u8), String> = HashMap::new();
+        for periph in &core.peripherals {
+            for ch in &periph.dma_channels {
+                let dmamux = ch.dmamux.as_deref().unwrap_or_default();
+                if !dmamux.is_empty() && !core.peripherals.iter().any(|p| p.name == dmamux) {
+                    push(
+                        Check::Dma,
+                        format!("{}.{} refers to unknown {}", periph.name, ch.signal, dmamux),
+                    );
+                }
+                let this = format!("{}.{}", periph.name, ch.signal);
+                if let Some(other) = requests.insert((dmamux, ch.request), this.clone()) {
+                    push(
+                        Check::Dma,
+                        format!(
+                            "{} request {} used by both {} and {}",
+                            dmamux, ch.request, other, this
+                        ),
+                    );
+                }
+            }
+        }
+
+        // peripheral address ranges, size taken from the register block when known
+        let mut ranges: Vec<(u32, u64, &str)> = core
+            .peripherals
+            .iter()
+            .map(|p| {
+                let size = p
+                    .registers
+                    .as_ref()
+                    .and_then(|r| {
+                        let ir = registers
+                            .registers
+                            .get(&format!("{}_{}", r.kind, r.version))?;
+                        block_size(ir, &r.block)
+                    })
+                    .unwrap_or(1);
+                (p.address, p.address as u64 + size as u64, p.name.as_str())
+            })
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            let (start, end, name) = pair[0];
+            let (next_start, _, next_name) = pair[1];
+            if start == next_start {
+                push(
+                    Check::Address,
+                    format!("{} and {} share address 0x{:08x}", name, next_name, start),
+                );
+            } else if end > next_start as u64 {
+                push(
+                    Check::Address,
+                    format!(
+                        "{} (0x{:08x}..0x{:08x}) overlaps {} at 0x{:08x}",
+                        name, start, end, next_name, next_start
+                    ),
+                );
+            }
+        }
+
+        // sysctl resources and clocks
+        let resources: HashSet<usize> = core.resources.iter().map(|r| r.index).collect();
+        let clocks: HashSet<usize> = core.clocks.iter().map(|c| c.index).collect();
+        for periph in &core.peripherals {
+            let Some(sysctl) = &periph.sysctl else {
+                continue;
+            };
+            if !resources.contains(&sysctl.resource) {
+                push(
+                    Check::Sysctl,
+                    format!(
+                        "{} refers to unknown resource {}",
+                        periph.name, sysctl.resource
+                    ),
+                );
+            }
+            if let Some(res) = sysctl.resource_clock_top {
+                if !resources.contains(&res) {
+                    push(
+                        Check::Sysctl,
+                        format!(
+                            "{} refers to unknown clock top resource {}",
+                            periph.name, res
+                        ),
+                    );
+                }
+            }
+            if let Some(clock) = sysctl.clock_node {
+                if !clocks.contains(&clock) {
+                    push(
+                        Check::Sysctl,
+                        format!("{} refers to unknown clock node {}", periph.name, clock),
+                    );
+                }
+            }
+        }
+
+        // each core has its own MCHTMR, its clock follows the hart, see `sysctl::sysctl_name`
+        if let Some(mchtmr) = core.peripherals.iter().find(|p| p.name == "MCHTMR") {
+            if mchtmr.sysctl.as_ref().and_then(|s| s.clock_node).is_none() {
+                push(
+                    Check::Sysctl,
+                    format!("MCHTMR of hart {} has no clock node", core.hart_id),
+                );
+            }
+        }
+
+        // pins against IOC pads
+        let pads: HashSet<&str> = core.pins.iter().map(|p| p.name.as_str()).collect();
+        for periph in &core.peripherals {
+            for pin in &periph.pins {
+                let pad = pin.pin.to_string();
+                if !pads.contains(pad.as_str()) {
+                    push(
+                        Check::Pin,
+                        format!("{}.{} on unknown pad {}", periph.name, pin.signal, pad),
+                    );
+                }
+            }
+        }
+        for package in &chip.packages {
+            if package.pinout.len() > package.pins as usize {
+                push(
+                    Check::Pin,
+                    format!(
+                        "package {} has {} pads bonded out, but only {} pins",
+                        package.name,
+                        package.pinout.len(),
+                        package.pins
+                    ),
+                );
+            }
+            let mut seen = HashSet::new();
+            for pin in package.pinout.iter().filter(|p| !seen.insert(&p.pin)) {
+                push(
+                    Check::Pin,
+                    format!(
+                        "package {} pin {} used more than once",
+                        package.name, pin.pin
+                    ),
+                );
+            }
+            for pin in &package.pinout {
+                let pad = pin.pad.to_string();
+                if !pads.contains(pad.as_str()) {
+                    push(
+                        Check::Pin,
+                        format!(
+                            "package {} pin {} on unknown pad {}",
+                            package.name, pin.pin, pad
+                        ),
+                    );
+                }
+            }
+        }
+
+        // register kinds against data/registers
+        for periph in &core.peripherals {
+            let Some(regs) = &periph.registers else {
+                continue;
+            };
+            let name = format!("{}_{}", regs.kind, regs.version);
+            match registers.registers.get(&name) {
+                None => push(
+                    Check::Registers,
+                    format!(
+                        "{} refers to missing data/registers/{}.yaml",
+                        periph.name, name
+                    ),
+                ),
+                Some(ir) if !ir.blocks.contains_key(&regs.block) => push(
+                    Check::Registers,
+                    format!(
+                        "{} refers to missing block {} in {}",
+                        periph.name, regs.block, name
+                    ),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    // cores must not share the clock of one MCHTMR, like the SDK's `CLK_TOP_MCHTMR` of HPM6700
+    let mut mchtmr_clocks: HashMap<usize, &str> = HashMap::new();
+    for core in &chip.cores {
+        let clock = core
+            .peripherals
+            .iter()
+            .find(|p| p.name == "MCHTMR")
+            .and_then(|p| p.sysctl.as_ref()?.clock_node);
+        if let Some(clock) = clock {
+            if let Some(other) = mchtmr_clocks.insert(clock, core.name.as_str()) {
+                report.push(
+                    &chip.name,
+                    &core.name,
+                    Check::Sysctl,
+                    format!("MCHTMR clock node {} shared with {}", clock, other),
+                );
+            }
+        }
+    }
+}
+
+/// Size in bytes spanned by a register block, including nested blocks and arrays.
+fn block_size(ir: &IR, name: &str) -> Option<u32> {
+    let block = ir.blocks.get(name)?;
+    let mut size = 0;
+    for item in &block.items {
+        let item_size = match &item.inner {
+            BlockItemInner::Register(reg) => reg.bit_size / 8,
+            BlockItemInner::Block(b) => block_size(ir, &b.block)?,
+        };
+        let item_size = match &item.array {
+            Some(Array::Regular(arr)) => item_size + arr.stride * arr.len.saturating_sub(1),
+            Some(Array::Cursed(arr)) => item_size + arr.offsets.iter().max().copied().unwrap_or(0),
+            None => item_size,
+        };
+        size = size.max(item.byte_offset + item_size);
+    }
+    Some(size)
+}
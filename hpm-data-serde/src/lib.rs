@@ -12,7 +12,14 @@ pub struct Chip {
     pub memory: Vec<chip::Memory>,
     // pub docs: Vec<chip::Doc>,
     pub cores: Vec<chip::Core>,
+    /// XPI0 NOR boot layout, from the family SDK layout unless set by the chip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boot: Option<chip::Boot>,
     // pub _raw: HashMap<String, String>,
+
+    // include fields, SDK layout and pinmux source of the chip family
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_sdk: Option<String>,
 }
 
 pub mod chip {
@@ -24,6 +31,27 @@ pub mod chip {
     pub struct Package {
         pub name: String,
         pub package: String,
+        pub pins: u32,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub pinout: Vec<package::Pin>,
+
+        // include fields, pinouts are shared by chips with the same package
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub include_pinout: Option<String>,
+    }
+
+    pub mod package {
+        use serde::{Deserialize, Serialize};
+
+        use super::core::peripheral::pin;
+
+        #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        pub struct Pin {
+            /// Physical pin number or ball name, like "35" or "A1"
+            pub pin: String,
+            /// IOC pad bonded to this pin
+            pub pad: pin::Pin,
+        }
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
@@ -46,6 +74,17 @@ pub mod chip {
         }
     }
 
+    /// Offsets from the XPI0 flash base used by the boot ROM
+    #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+    pub struct Boot {
+        /// XPI NOR configuration option block
+        pub nor_cfg_option_offset: u32,
+        /// boot header, immediately followed by the firmware info table
+        pub boot_header_offset: u32,
+        /// application image
+        pub app_offset: u32,
+    }
+
     #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct Doc {
         pub r#type: String,
@@ -57,6 +96,12 @@ pub mod chip {
     #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct Core {
         pub name: String,
+        /// Hart ID of the core, also its PLIC target
+        #[serde(default)]
+        pub hart_id: u8,
+        /// Core-local view of ILM/DLM, overrides the chip memory region of the same name
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub memory: Vec<Memory>,
         #[serde(default)]
         pub peripherals: Vec<core::Peripheral>,
         #[serde(default)]
@@ -75,6 +120,14 @@ pub mod chip {
         pub iomuxes: Vec<core::IoMux>,
         #[serde(default)]
         pub trgmmuxes: Vec<core::TrgmMux>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub clock_tree: Option<core::ClockTree>,
+        /// `hpm_soc_feature.h` defines not belonging to a peripheral, by full name
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub params: BTreeMap<String, u32>,
+        /// `HPM_IP_FEATURE_*` flags not belonging to a peripheral, without the prefix
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub features: Vec<String>,
 
         // include fields, for common peripherals
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -90,6 +143,8 @@ pub mod chip {
     }
 
     pub mod core {
+        use std::collections::BTreeMap;
+
         use serde::{Deserialize, Serialize};
 
         #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
@@ -108,6 +163,12 @@ pub mod chip {
             pub interrupts: Option<Vec<peripheral::Interrupt>>, // TODO: This should just be a Vec
             #[serde(default, skip_serializing_if = "Vec::is_empty")]
             pub dma_channels: Vec<peripheral::DmaChannel>,
+            /// `hpm_soc_feature.h` `<KIND>_SOC_*` defines, without the prefix
+            #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+            pub params: BTreeMap<String, u32>,
+            /// `HPM_IP_FEATURE_<KIND>_*` flags, without `HPM_IP_FEATURE_`
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub features: Vec<String>,
         }
 
         pub mod peripheral {
@@ -281,6 +342,79 @@ pub mod chip {
             // signal offset
             pub value: u8,
         }
+
+        /// Clock sources, PLLs and `SYSCTL.CLOCK` nodes
+        #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        pub struct ClockTree {
+            // selectable by the MUX of every clock node
+            pub sources: Vec<clock_tree::Source>,
+            pub plls: Vec<clock_tree::Pll>,
+            pub nodes: Vec<clock_tree::Node>,
+            // clocks with their own source groups, like ADC and I2S
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub muxes: Vec<clock_tree::Mux>,
+            // datasheet limits, of the nodes that have one
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub max_frequencies: Vec<clock_tree::MaxFrequency>,
+        }
+
+        pub mod clock_tree {
+            use serde::{Deserialize, Serialize};
+
+            #[derive(
+                Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            )]
+            pub struct Source {
+                pub name: String,
+                // MUX value
+                pub index: usize,
+            }
+
+            #[derive(
+                Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            )]
+            pub struct Pll {
+                pub name: String,
+                // PLLCTL.PLL
+                pub index: usize,
+                // clock sources driven by the PLL, like PLL0_CLK0
+                pub outputs: Vec<String>,
+                // post divider width in bits
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub div_width: Option<u8>,
+            }
+
+            #[derive(
+                Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            )]
+            pub struct Node {
+                pub name: String,
+                // SYSCTL.CLOCK
+                pub index: usize,
+                pub mux_width: u8,
+                pub div_width: u8,
+                // peripherals with this node as `sysctl.clock_node`
+                #[serde(default, skip_serializing_if = "Vec::is_empty")]
+                pub peripherals: Vec<String>,
+            }
+
+            #[derive(
+                Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            )]
+            pub struct Mux {
+                pub name: String,
+                pub sources: Vec<Source>,
+            }
+
+            #[derive(
+                Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            )]
+            pub struct MaxFrequency {
+                pub node: String,
+                // in Hz
+                pub frequency: u32,
+            }
+        }
     }
 }
 
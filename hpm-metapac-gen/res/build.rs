@@ -1,5 +1,4 @@
 use std::env;
-#[cfg(any(feature = "rt", feature = "memory-x"))]
 use std::path::PathBuf;
 
 enum GetOneError {
@@ -24,22 +23,45 @@ impl<T: Iterator> IteratorExt for T {
 }
 
 fn main() {
-    #[cfg(any(feature = "rt", feature = "memory-x"))]
     let crate_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let chips_dir = crate_dir.join("src/chips");
 
-    let chip_core_name = match env::vars()
+    // Both chip and package features start with `hpm`, like `hpm5361` and `hpm5361xcfx`
+    let hpm_features: Vec<String> = env::vars()
         .map(|(a, _)| a)
         .filter(|x| x.starts_with("CARGO_FEATURE_HPM"))
+        .map(|x| {
+            x.strip_prefix("CARGO_FEATURE_")
+                .unwrap()
+                .to_ascii_lowercase()
+                .replace('_', "-")
+        })
+        .collect();
+
+    let chip_core_name = match hpm_features
+        .iter()
+        .filter(|x| chips_dir.join(x).is_dir())
         .get_one()
     {
-        Ok(x) => x,
+        Ok(x) => x.clone(),
         Err(GetOneError::None) => panic!("No hpmxxxx Cargo feature enabled"),
         Err(GetOneError::Multiple) => panic!("Multiple hpmxxxx Cargo features enabled"),
-    }
-    .strip_prefix("CARGO_FEATURE_")
-    .unwrap()
-    .to_ascii_lowercase()
-    .replace('_', "-");
+    };
+
+    let packages_dir = chips_dir.join(&chip_core_name).join("packages");
+    let package_name = match hpm_features
+        .iter()
+        .filter(|x| **x != chip_core_name)
+        .get_one()
+    {
+        Ok(x) if packages_dir.join(x).is_dir() => Some(x.clone()),
+        Ok(x) => panic!(
+            "Package feature {} is not a package of {}",
+            x, chip_core_name
+        ),
+        Err(GetOneError::None) => None,
+        Err(GetOneError::Multiple) => panic!("Multiple package Cargo features enabled"),
+    };
 
     #[cfg(feature = "rt")]
     println!(
@@ -49,18 +71,55 @@ fn main() {
     );
 
     #[cfg(feature = "memory-x")]
+    {
+        let layout = match (
+            cfg!(feature = "memory-x-ram"),
+            cfg!(feature = "memory-x-flash-sdram-xip"),
+        ) {
+            (false, false) => "flash_xip",
+            (true, false) => "ram",
+            (false, true) => "flash_sdram_xip",
+            (true, true) => panic!("Multiple memory-x layout Cargo features enabled"),
+        };
+        let memory_x_dir = chips_dir
+            .join(&chip_core_name)
+            .join("memory_x")
+            .join(layout);
+        if !memory_x_dir.is_dir() {
+            panic!("No {} memory.x for {}", layout, chip_core_name);
+        }
+        println!("cargo:rustc-link-search={}", memory_x_dir.display());
+    }
     println!(
-        "cargo:rustc-link-search={}/src/chips/{}/memory_x/",
-        crate_dir.display(),
+        "cargo:rustc-env=HPM_METAPAC_PAC_PATH=chips/{}/pac.rs",
         chip_core_name
     );
+
+    // pins and metadata are narrowed down to the bonded pads of the selected package
+    let pins_dir = match &package_name {
+        Some(package_name) => format!("chips/{}/packages/{}", chip_core_name, package_name),
+        None => format!("chips/{}", chip_core_name),
+    };
+    println!("cargo:rustc-env=HPM_METAPAC_PINS_PATH={}/pins.rs", pins_dir);
     println!(
-        "cargo:rustc-env=HPM_METAPAC_PAC_PATH=chips/{}/pac.rs",
-        chip_core_name
+        "cargo:rustc-env=HPM_METAPAC_METADATA_PATH={}/metadata.rs",
+        pins_dir
     );
+
+    // peri_uart4, uart_v53, family_hpm5300, sub_family_hpm5300_single_core_basic
+    let all_cfgs = std::fs::read_to_string(chips_dir.join("all_cfgs.txt")).unwrap();
+    for cfg in all_cfgs.lines() {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+    }
+    let cfgs = std::fs::read_to_string(chips_dir.join(&chip_core_name).join("cfgs.txt")).unwrap();
+    for cfg in cfgs.lines() {
+        println!("cargo:rustc-cfg={}", cfg);
+    }
+    // cfgs only apply to this crate, dependents re-emit them from `DEP_HPM_METAPAC_CFGS`
+    println!("cargo:cfgs={}", cfgs.lines().collect::<Vec<_>>().join(","));
     println!(
-        "cargo:rustc-env=HPM_METAPAC_METADATA_PATH=chips/{}/metadata.rs",
-        chip_core_name
+        "cargo:all_cfgs={}",
+        all_cfgs.lines().collect::<Vec<_>>().join(",")
     );
 
     println!("cargo:rerun-if-changed=build.rs");
@@ -0,0 +1,147 @@
+// Boot ROM structures, included by the `boot_header` module of each chip after its offsets.
+//
+// A bootable image executing in place from XPI0 needs:
+//
+// ```rust,ignore
+// use hpm_metapac::boot_header::*;
+//
+// #[link_section = ".nor_cfg_option"]
+// #[used]
+// static NOR_CFG_OPTION: NorCfgOption = NorCfgOption::new(0x0000_0007, 0x0000_0000);
+//
+// #[link_section = ".boot_header"]
+// #[used]
+// static BOOT_HEADER: BootHeader = BootHeader::new(1);
+//
+// hpm_metapac::xip_fw_info_table!();
+// ```
+//
+// and `-Tboot_header.x` in the linker arguments. The firmware info table takes the image size from
+// the linker, a `#[link_section = ".fw_info_table"]` static of [`FwInfoTable::xip`] can replace it.
+
+/// `tag` of [`BootHeader`]
+pub const BOOT_HEADER_TAG: u8 = 0xBF;
+/// `version` of [`BootHeader`]
+pub const BOOT_HEADER_VERSION: u8 = 0x10;
+/// Tag in the upper half of the first word of [`NorCfgOption`]
+pub const NOR_CFG_OPTION_TAG: u32 = 0xFCF9_0000;
+
+/// XPI NOR configuration option, read by the boot ROM to probe the serial flash.
+///
+/// `option0` and `option1` depend on the flash device and its wiring, see `board.c` of the
+/// board in hpm_sdk, e.g. `0x0000_0007, 0` for a quad SPI flash on XPI0 port A.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct NorCfgOption {
+    pub header: u32,
+    pub option0: u32,
+    pub option1: u32,
+    pub option2: u32,
+}
+
+impl NorCfgOption {
+    pub const fn new(option0: u32, option1: u32) -> Self {
+        Self {
+            // number of option words that follow the header
+            header: NOR_CFG_OPTION_TAG | 2,
+            option0,
+            option1,
+            option2: 0,
+        }
+    }
+}
+
+/// Boot header, immediately followed by `fw_count` [`FwInfoTable`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct BootHeader {
+    pub tag: u8,
+    pub version: u8,
+    pub length: u16,
+    pub flags: u32,
+    pub sw_version: u16,
+    pub fuse_version: u8,
+    pub fw_count: u8,
+    pub dc_block_offset: u16,
+    pub sig_block_offset: u16,
+}
+
+impl BootHeader {
+    pub const fn new(fw_count: u8) -> Self {
+        Self {
+            tag: BOOT_HEADER_TAG,
+            version: BOOT_HEADER_VERSION,
+            length: (core::mem::size_of::<BootHeader>()
+                + fw_count as usize * core::mem::size_of::<FwInfoTable>())
+                as u16,
+            flags: 0,
+            sw_version: 0,
+            fuse_version: 0,
+            fw_count,
+            dc_block_offset: 0,
+            sig_block_offset: 0,
+        }
+    }
+}
+
+/// Firmware image description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct FwInfoTable {
+    /// offset of the image from the boot header
+    pub offset: u32,
+    /// size of the image in bytes
+    pub size: u32,
+    /// `[3:0]` image type, `[11:8]` hash type, 0 for an executable without hash
+    pub flags: u32,
+    pub reserved0: u32,
+    pub load_addr: u32,
+    pub reserved1: u32,
+    pub entry_point: u32,
+    pub reserved2: u32,
+    pub hash: [u8; 64],
+    pub iv: [u8; 32],
+}
+
+/// [`FwInfoTable`] of the image at `APP_OFFSET` executing in place, in the `.fw_info_table` section.
+/// Like the SDK's `fw_info`, offset, size, load address and entry point are the `__app_offset__`,
+/// `__fw_size__`, `__app_load_addr__` and `__app_entry_point__` symbols of `boot_header.x`.
+#[cfg(target_arch = "riscv32")]
+#[macro_export]
+macro_rules! xip_fw_info_table {
+    () => {
+        core::arch::global_asm!(
+            ".pushsection .fw_info_table, \"a\"",
+            ".balign 4",
+            ".word __app_offset__",
+            ".word __fw_size__",
+            ".word 0", // flags, executable without hash
+            ".word 0",
+            ".word __app_load_addr__",
+            ".word 0",
+            ".word __app_entry_point__",
+            ".word 0",
+            ".zero 96", // hash and iv
+            ".popsection",
+        );
+    };
+}
+
+impl FwInfoTable {
+    /// Image of `size` bytes at `APP_OFFSET`, executing in place.
+    pub const fn xip(size: u32) -> Self {
+        let app_addr = (crate::FLASH_BASE + APP_OFFSET) as u32;
+        Self {
+            offset: (APP_OFFSET - BOOT_HEADER_OFFSET) as u32,
+            size,
+            flags: 0,
+            reserved0: 0,
+            load_addr: app_addr,
+            reserved1: 0,
+            entry_point: app_addr,
+            reserved2: 0,
+            hash: [0; 64],
+            iv: [0; 32],
+        }
+    }
+}
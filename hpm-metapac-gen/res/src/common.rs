@@ -0,0 +1,107 @@
+// Register access of the generated peripherals, the API of chiptool's common module with `modify`
+// leaving write-one-to-clear bits alone.
+
+use core::marker::PhantomData;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct RW;
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct R;
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct W;
+
+mod sealed {
+    use super::*;
+    pub trait Access {}
+    impl Access for R {}
+    impl Access for W {}
+    impl Access for RW {}
+}
+
+pub trait Access: sealed::Access + Copy {}
+impl Access for R {}
+impl Access for W {}
+impl Access for RW {}
+
+pub trait Read: Access {}
+impl Read for RW {}
+impl Read for R {}
+
+pub trait Write: Access {}
+impl Write for RW {}
+impl Write for W {}
+
+/// Register values, fieldsets implement it from the `oneToClear` fields of the SVD
+pub trait WriteOneToClear: Copy {
+    /// The value with its write-one-to-clear bits zeroed, writing it back clears no flag
+    #[inline(always)]
+    fn without_w1c(self) -> Self {
+        self
+    }
+}
+impl WriteOneToClear for u8 {}
+impl WriteOneToClear for u16 {}
+impl WriteOneToClear for u32 {}
+impl WriteOneToClear for u64 {}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Reg<T: Copy, A: Access> {
+    ptr: *mut u8,
+    phantom: PhantomData<*mut (T, A)>,
+}
+unsafe impl<T: Copy, A: Access> Send for Reg<T, A> {}
+unsafe impl<T: Copy, A: Access> Sync for Reg<T, A> {}
+
+impl<T: Copy, A: Access> Reg<T, A> {
+    #[allow(clippy::missing_safety_doc)]
+    #[inline(always)]
+    pub const unsafe fn from_ptr(ptr: *mut T) -> Self {
+        Self {
+            ptr: ptr as _,
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn as_ptr(&self) -> *mut T {
+        self.ptr as _
+    }
+}
+
+impl<T: Copy, A: Read> Reg<T, A> {
+    #[inline(always)]
+    pub fn read(&self) -> T {
+        unsafe { (self.ptr as *mut T).read_volatile() }
+    }
+}
+
+impl<T: Copy, A: Write> Reg<T, A> {
+    #[inline(always)]
+    pub fn write_value(&self, val: T) {
+        unsafe { (self.ptr as *mut T).write_volatile(val) }
+    }
+}
+
+impl<T: Default + Copy, A: Write> Reg<T, A> {
+    /// Writes the value `f` sets on the `Default`, the reset value without its
+    /// write-one-to-clear bits
+    #[inline(always)]
+    pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut val = Default::default();
+        let res = f(&mut val);
+        self.write_value(val);
+        res
+    }
+}
+
+impl<T: WriteOneToClear, A: Read + Write> Reg<T, A> {
+    /// Read-modify-write. The write-one-to-clear bits read are zeroed before `f`, the flags
+    /// they are set on stay set unless `f` sets them.
+    #[inline(always)]
+    pub fn modify<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut val = self.read().without_w1c();
+        let res = f(&mut val);
+        self.write_value(val);
+        res
+    }
+}
@@ -10,15 +10,48 @@ pub mod common;
 #[cfg(feature = "pac")]
 include!(env!("HPM_METAPAC_PAC_PATH"));
 
+// `pins` and `iomux`, restricted to the bonded pads when a package feature is enabled
+#[cfg(feature = "pac")]
+include!(env!("HPM_METAPAC_PINS_PATH"));
+
 #[cfg(feature = "metadata")]
 pub mod metadata {
     include!("metadata.rs");
     include!(env!("HPM_METAPAC_METADATA_PATH"));
 }
 
-pub unsafe trait InterruptNumber: Copy {
-    /// Return the interrupt number associated with this variant.
-    ///
-    /// See trait documentation for safety requirements.
-    fn number(self) -> u16;
+// `Interrupt`, `CoreInterrupt`, `Exception` and `Hart` of the chip implement these
+pub use riscv_pac::{
+    CoreInterruptNumber, ExceptionNumber, ExternalInterruptNumber, HartIdNumber, InterruptNumber,
+};
+
+/// Copies `.fast` from its load address in flash to ILM, riscv-rt does not.
+/// Call it from the `#[riscv_rt::pre_init]` function when `memory.x` of the `memory-x` features is used.
+///
+/// # Safety
+///
+/// Must run before any code in `.fast`, and not while it is executing.
+#[cfg(all(feature = "memory-x", target_arch = "riscv32"))]
+#[inline(always)]
+pub unsafe fn copy_fast_section() {
+    core::arch::asm!(
+        "la {dst}, __fast_start__",
+        "la {end}, __fast_end__",
+        "la {src}, __fast_load_addr__",
+        "beq {dst}, {src}, 2f", // already in place, the `memory-x-ram` layout
+        "1:",
+        "bgeu {dst}, {end}, 2f",
+        "lw {tmp}, 0({src})",
+        "sw {tmp}, 0({dst})",
+        "addi {dst}, {dst}, 4",
+        "addi {src}, {src}, 4",
+        "j 1b",
+        "2:",
+        // `fence.i`, spelled out as targets without Zifencei reject the mnemonic
+        ".insn i 0x0f, 1, x0, x0, 0",
+        dst = out(reg) _,
+        end = out(reg) _,
+        src = out(reg) _,
+        tmp = out(reg) _,
+    );
 }
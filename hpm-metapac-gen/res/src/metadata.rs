@@ -37,6 +37,8 @@ pub mod ir {
         pub access: Access,
         pub bit_size: u32,
         pub fieldset: Option<&'static str>,
+        /// `None` when the reset value is zero
+        pub reset_value: Option<u64>,
     }
 
     #[derive(Debug, Eq, PartialEq, Clone)]
@@ -70,6 +72,31 @@ pub mod ir {
         pub bit_size: u32,
         pub array: Option<Array>,
         pub enumm: Option<&'static str>,
+        /// SVD `modifiedWriteValues`, `None` for a plain write
+        pub modified_write_values: Option<ModifiedWriteValues>,
+        /// SVD `readAction`, `None` when reading has no side effect
+        pub read_action: Option<ReadAction>,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub enum ModifiedWriteValues {
+        OneToClear,
+        OneToSet,
+        OneToToggle,
+        ZeroToClear,
+        ZeroToSet,
+        ZeroToToggle,
+        Clear,
+        Set,
+        Modify,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub enum ReadAction {
+        Clear,
+        Set,
+        Modify,
+        ModifyExternal,
     }
 
     #[derive(Debug, Eq, PartialEq, Clone)]
@@ -125,7 +152,12 @@ pub mod ir {
 pub struct Metadata {
     pub name: &'static str,
     pub family: &'static str,
+    pub core: &'static str,
+    /// Hart ID of the core, also its PLIC target
+    pub hart_id: u8,
+    /// Memory as seen by the core, ILM/DLM are its own local memories
     pub memory: &'static [MemoryRegion],
+    pub packages: &'static [Package],
     pub peripherals: &'static [Peripheral],
     pub interrupts: &'static [Interrupt],
     pub dma_channels: &'static [DmaChannel],
@@ -133,6 +165,63 @@ pub struct Metadata {
     pub clocks: &'static [Clock],
     pub pins: &'static [IoPin],
     pub trgmmux: &'static [TrgmMux],
+    pub clock_tree: Option<&'static ClockTree>,
+    /// `hpm_soc_feature.h` defines not belonging to a peripheral
+    pub params: &'static [Param],
+    /// `HPM_IP_FEATURE_*` flags not belonging to a peripheral, without the prefix
+    pub features: &'static [&'static str],
+}
+
+/// Clock sources, PLLs and `SYSCTL.CLOCK` nodes
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ClockTree {
+    /// Selectable by the MUX of every clock node
+    pub sources: &'static [ClockSource],
+    pub plls: &'static [Pll],
+    pub nodes: &'static [ClockNode],
+    /// Clocks with their own source groups, like ADC and I2S
+    pub muxes: &'static [ClockMux],
+    /// Datasheet limits, of the nodes that have one
+    pub max_frequencies: &'static [MaxFrequency],
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ClockSource {
+    pub name: &'static str,
+    pub index: u32,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Pll {
+    pub name: &'static str,
+    pub index: u32,
+    /// Clock sources driven by the PLL, like `PLL0_CLK0`
+    pub outputs: &'static [&'static str],
+    /// Post divider width in bits
+    pub div_width: Option<u8>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ClockNode {
+    pub name: &'static str,
+    pub index: u32,
+    pub mux_width: u8,
+    pub div_width: u8,
+    /// Peripherals clocked by this node, the `CLOCK_NODE` of their `info::` module
+    pub peripherals: &'static [&'static str],
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ClockMux {
+    pub name: &'static str,
+    pub sources: &'static [ClockSource],
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MaxFrequency {
+    pub node: &'static str,
+    /// In Hz
+    pub frequency: u32,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -183,6 +272,14 @@ pub struct Interrupt {
 pub struct Package {
     pub name: &'static str,
     pub package: &'static str,
+    pub pins: u32,
+    pub pinout: &'static [PackagePin],
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PackagePin {
+    pub pin: &'static str,
+    pub pad: &'static str,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -194,6 +291,17 @@ pub struct Peripheral {
     pub pins: &'static [PeripheralPin],
     pub dma_channels: &'static [PeripheralDmaChannel],
     pub interrupts: &'static [PeripheralInterrupt],
+    /// `hpm_soc_feature.h` `<KIND>_SOC_*` defines, without the prefix
+    pub params: &'static [Param],
+    /// `HPM_IP_FEATURE_<KIND>_*` flags, without `HPM_IP_FEATURE_`
+    pub features: &'static [&'static str],
+}
+
+/// A numeric define of `hpm_soc_feature.h`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Param {
+    pub name: &'static str,
+    pub value: u32,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -4,6 +4,17 @@ use serde::Deserialize;
 pub mod ir {
     use super::*;
 
+    /// What chiptool does not keep, from the extra keys of build/data/registers
+    #[derive(Debug, Default)]
+    pub struct Extras {
+        /// (block, register) => `reset_value`
+        pub reset_values: std::collections::HashMap<(String, String), u64>,
+        /// (fieldset, field) => `modified_write_values`
+        pub modified_write_values: std::collections::HashMap<(String, String), ModifiedWriteValues>,
+        /// (fieldset, field) => `read_action`
+        pub read_actions: std::collections::HashMap<(String, String), ReadAction>,
+    }
+
     #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
     pub struct IR {
         pub blocks: Vec<Block>,
@@ -12,7 +23,7 @@ pub mod ir {
     }
 
     impl IR {
-        pub fn from_chiptool(ir: chiptool::ir::IR) -> Self {
+        pub fn from_chiptool(ir: chiptool::ir::IR, extras: &Extras) -> Self {
             let mut blocks: Vec<Block> = ir
                 .blocks
                 .iter()
@@ -54,6 +65,10 @@ pub mod ir {
                                         fieldset: register.fieldset.as_ref().map(|fieldset| {
                                             fieldset.strip_prefix("regs::").unwrap().to_string()
                                         }),
+                                        reset_value: extras
+                                            .reset_values
+                                            .get(&(name.to_string(), item.name.clone()))
+                                            .copied(),
                                     })
                                 }
                             },
@@ -76,6 +91,7 @@ pub mod ir {
                 .fieldsets
                 .iter()
                 .map(|(name, fieldset)| {
+                    let fieldset_name = name.strip_prefix("regs::").unwrap();
                     let fields = fieldset
                         .fields
                         .iter()
@@ -109,12 +125,20 @@ pub mod ir {
                             enumm: field.enumm.as_ref().map(|fieldset| {
                                 fieldset.strip_prefix("vals::").unwrap().to_string()
                             }),
+                            modified_write_values: extras
+                                .modified_write_values
+                                .get(&(fieldset_name.to_string(), field.name.clone()))
+                                .copied(),
+                            read_action: extras
+                                .read_actions
+                                .get(&(fieldset_name.to_string(), field.name.clone()))
+                                .copied(),
                         })
                         .collect();
 
                     #[allow(clippy::redundant_field_names)]
                     FieldSet {
-                        name: name.strip_prefix("regs::").unwrap().to_owned(),
+                        name: fieldset_name.to_owned(),
                         fields: fields,
                         extends: fieldset.extends.clone(),
                         description: fieldset.description.clone(),
@@ -207,6 +231,7 @@ pub mod ir {
         pub access: Access,
         pub bit_size: u32,
         pub fieldset: Option<String>,
+        pub reset_value: Option<u64>,
     }
 
     #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
@@ -240,6 +265,32 @@ pub mod ir {
         pub bit_size: u32,
         pub array: Option<Array>,
         pub enumm: Option<String>,
+        pub modified_write_values: Option<ModifiedWriteValues>,
+        pub read_action: Option<ReadAction>,
+    }
+
+    // SVD spelling
+    #[derive(EnumDebug, Eq, PartialEq, Clone, Copy, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum ModifiedWriteValues {
+        OneToClear,
+        OneToSet,
+        OneToToggle,
+        ZeroToClear,
+        ZeroToSet,
+        ZeroToToggle,
+        Clear,
+        Set,
+        Modify,
+    }
+
+    #[derive(EnumDebug, Eq, PartialEq, Clone, Copy, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum ReadAction {
+        Clear,
+        Set,
+        Modify,
+        ModifyExternal,
     }
 
     #[derive(EnumDebug, Eq, PartialEq, Clone, Deserialize)]
@@ -295,9 +346,36 @@ pub mod ir {
 pub struct Chip {
     pub name: String,
     pub family: String,
+    pub sub_family: String,
     pub cores: Vec<Core>,
     pub memory: Vec<MemoryRegion>,
     pub packages: Vec<Package>,
+    #[serde(default)]
+    pub boot: Option<Boot>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct Boot {
+    pub nor_cfg_option_offset: u32,
+    pub boot_header_offset: u32,
+    pub app_offset: u32,
+}
+
+impl Chip {
+    /// Memory as seen by `core`: its own ILM/DLM replace the chip-level regions of the same
+    /// name, with or without an index, e.g. `ILM` replaces `ILM0` and `ILM1`.
+    pub fn core_memory(&self, core: &Core) -> Vec<MemoryRegion> {
+        let mut memory = Vec::new();
+        for region in &self.memory {
+            let base = region.name.trim_end_matches(|c: char| c.is_ascii_digit());
+            match core.memory.iter().find(|r| r.name == base) {
+                Some(local) if !memory.contains(local) => memory.push(local.clone()),
+                Some(_) => {}
+                None => memory.push(region.clone()),
+            }
+        }
+        memory
+    }
 }
 
 // Notice:
@@ -335,6 +413,10 @@ pub enum MemoryRegionKind {
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
 pub struct Core {
     pub name: String,
+    #[serde(default)]
+    pub hart_id: u8,
+    #[serde(default)]
+    pub memory: Vec<MemoryRegion>,
     pub peripherals: Vec<Peripheral>,
     #[serde(default)]
     pub interrupts: Vec<Interrupt>,
@@ -350,6 +432,60 @@ pub struct Core {
     pub iomuxes: Vec<IoMux>,
     #[serde(default)]
     pub trgmmuxes: Vec<TrgmMux>,
+    #[serde(default)]
+    pub clock_tree: Option<ClockTree>,
+    #[serde(default)]
+    pub params: std::collections::BTreeMap<String, u32>,
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct ClockTree {
+    pub sources: Vec<ClockSource>,
+    pub plls: Vec<Pll>,
+    pub nodes: Vec<ClockNode>,
+    #[serde(default)]
+    pub muxes: Vec<ClockMux>,
+    #[serde(default)]
+    pub max_frequencies: Vec<MaxFrequency>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct ClockSource {
+    pub name: String,
+    pub index: u32,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct Pll {
+    pub name: String,
+    pub index: u32,
+    pub outputs: Vec<String>,
+    #[serde(default)]
+    pub div_width: Option<u8>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct ClockNode {
+    pub name: String,
+    pub index: u32,
+    pub mux_width: u8,
+    pub div_width: u8,
+    #[serde(default)]
+    pub peripherals: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct ClockMux {
+    pub name: String,
+    pub sources: Vec<ClockSource>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct MaxFrequency {
+    pub node: String,
+    pub frequency: u32,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
@@ -390,6 +526,15 @@ pub struct Interrupt {
 pub struct Package {
     pub name: String,
     pub package: String,
+    pub pins: u32,
+    #[serde(default)]
+    pub pinout: Vec<PackagePin>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
+pub struct PackagePin {
+    pub pin: String,
+    pub pad: String,
 }
 
 // Notice:
@@ -409,6 +554,10 @@ pub struct Peripheral {
     pub dma_channels: Vec<PeripheralDmaChannel>,
     #[serde(default)]
     pub interrupts: Vec<PeripheralInterrupt>,
+    #[serde(default)]
+    pub params: std::collections::BTreeMap<String, u32>,
+    #[serde(default)]
+    pub features: Vec<String>,
 }
 
 // Notice:
@@ -423,10 +572,27 @@ impl std::fmt::Debug for Peripheral {
             .field("pins", &self.pins)
             .field("dma_channels", &self.dma_channels)
             .field("interrupts", &self.interrupts)
+            .field("params", &params(&self.params))
+            .field("features", &self.features)
             .finish()
     }
 }
 
+/// `Param` of metadata
+#[derive(Debug)]
+pub struct Param<'a> {
+    pub name: &'a str,
+    pub value: u32,
+}
+
+/// A map prints as a slice of `Param`
+pub fn params(params: &std::collections::BTreeMap<String, u32>) -> Vec<Param<'_>> {
+    params
+        .iter()
+        .map(|(name, &value)| Param { name, value })
+        .collect()
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
 pub struct PeripheralInterrupt {
     pub signal: String,
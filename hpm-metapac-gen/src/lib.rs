@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::{Debug, Write as _};
 use std::fs;
 use std::fs::File;
@@ -8,12 +8,18 @@ use std::str::FromStr;
 
 use chiptool::generate::CommonModule;
 use chiptool::{generate, ir, transform};
-use proc_macro2::TokenStream;
+use proc_macro2::{Group, TokenStream, TokenTree};
 use regex::Regex;
 
 mod data;
 use data::*;
 
+// the common module of the PAC, for the tests of what it is generated with
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../res/src/common.rs"]
+mod common;
+
 /*
 #[derive(Debug, Eq, PartialEq, Clone)]
 struct Metadata<'a> {
@@ -36,6 +42,7 @@ pub struct Gen {
     opts: Options,
     all_peripheral_versions: HashSet<(String, String)>,
     metadata_dedup: HashMap<String, String>,
+    all_cfgs: BTreeSet<String>,
 }
 
 impl Gen {
@@ -44,31 +51,14 @@ impl Gen {
             opts,
             all_peripheral_versions: HashSet::new(),
             metadata_dedup: HashMap::new(),
+            all_cfgs: BTreeSet::new(),
         }
     }
 
     fn gen_chip(&mut self, chip_core_name: &str, chip: &Chip, core: &Core, _core_index: usize) {
-        let mut ir = ir::IR::new();
-
-        let mut dev = ir::Device {
-            // nvic_priority_bits: core.nvic_priority_bits,
-            interrupts: Vec::new(),
-            peripherals: Vec::new(),
-            nvic_priority_bits: None, // FIXME: not used for ch32
-        };
-
         let mut peripheral_versions: BTreeMap<String, String> = BTreeMap::new();
 
         for p in &core.peripherals {
-            let mut ir_peri = ir::Peripheral {
-                name: p.name.clone(),
-                array: None,
-                base_address: p.address,
-                block: None,
-                description: None,
-                interrupts: HashMap::new(),
-            };
-
             if let Some(bi) = &p.registers {
                 if let Some(old_version) =
                     peripheral_versions.insert(bi.kind.clone(), bi.version.clone())
@@ -80,22 +70,9 @@ impl Gen {
                         );
                     }
                 }
-                ir_peri.block = Some(format!("{}::{}", bi.kind, bi.block));
             }
-
-            dev.peripherals.push(ir_peri);
         }
 
-        for irq in &core.interrupts {
-            dev.interrupts.push(ir::Interrupt {
-                name: irq.name.clone(),
-                description: None,
-                value: irq.number,
-            });
-        }
-
-        ir.devices.insert("".to_string(), dev);
-
         let mut extra = format!("");
 
         for (module, version) in &peripheral_versions {
@@ -135,6 +112,30 @@ impl Gen {
             total_flash_size
         )
         .unwrap();
+        writeln!(&mut extra, "pub const HART_ID: usize = {};", core.hart_id).unwrap();
+
+        if let Some(boot) = &chip.boot {
+            writeln!(&mut extra, "pub mod boot_header {{").unwrap();
+            writeln!(
+                &mut extra,
+                "    //! XPI0 NOR boot layout, place the statics with `boot_header.x`"
+            )
+            .unwrap();
+            for (name, offset) in [
+                ("NOR_CFG_OPTION_OFFSET", boot.nor_cfg_option_offset),
+                ("BOOT_HEADER_OFFSET", boot.boot_header_offset),
+                ("APP_OFFSET", boot.app_offset),
+            ] {
+                writeln!(
+                    &mut extra,
+                    "    pub const {}: usize = 0x{:x};",
+                    name, offset
+                )
+                .unwrap();
+            }
+            writeln!(&mut extra, "    include!(\"../../boot_header.rs\");").unwrap();
+            writeln!(&mut extra, "}}").unwrap();
+        }
 
         // ==============================
         // Generate Register Block indices
@@ -167,52 +168,176 @@ impl Gen {
             }
             writeln!(&mut extra, "}}").unwrap();
 
-            // All pin pads
-            writeln!(&mut extra, "pub mod pins {{").unwrap();
-            writeln!(&mut extra, "    //! Pin pad definitions").unwrap();
-            for pin in &core.pins {
+            // ALL TRGMMUX consts
+            writeln!(&mut extra, "pub mod trgmmux {{").unwrap();
+            writeln!(&mut extra, "    //! `TRGMMUX` definitions").unwrap();
+            for mux in &core.trgmmuxes {
                 writeln!(
                     &mut extra,
                     "    pub const {}: usize = {};",
-                    pin.name.to_ascii_uppercase(),
-                    pin.index
-                )
-                .unwrap();
-            }
-            writeln!(&mut extra, "}}").unwrap();
-
-            // All iomux consts
-            writeln!(&mut extra, "pub mod iomux {{").unwrap();
-            writeln!(&mut extra, "    //! `FUNC_CTL` function mux definitions").unwrap();
-            for mux in &core.iomuxes {
-                writeln!(
-                    &mut extra,
-                    "    pub const {}: u8 = {};",
                     mux.name.to_ascii_uppercase(),
                     mux.value
                 )
                 .unwrap();
             }
             writeln!(&mut extra, "}}").unwrap();
+        }
 
-            // ALL TRGMMUX consts
-            writeln!(&mut extra, "pub mod trgmmux {{").unwrap();
-            writeln!(&mut extra, "    //! `TRGMMUX` definitions").unwrap();
-            for mux in &core.trgmmuxes {
+        // ==============================
+        // hpm_soc_feature.h params
+        {
+            writeln!(&mut extra, "pub mod params {{").unwrap();
+            writeln!(
+                &mut extra,
+                "    //! `hpm_soc_feature.h` defines, peripheral ones in a module per instance"
+            )
+            .unwrap();
+            for param in params(&core.params) {
                 writeln!(
                     &mut extra,
                     "    pub const {}: usize = {};",
-                    mux.name.to_ascii_uppercase(),
-                    mux.value
+                    param.name, param.value
                 )
                 .unwrap();
             }
+            for p in core.peripherals.iter().filter(|p| !p.params.is_empty()) {
+                writeln!(&mut extra, "    pub mod {} {{", p.name).unwrap();
+                for param in params(&p.params) {
+                    writeln!(
+                        &mut extra,
+                        "        pub const {}: usize = {};",
+                        param.name, param.value
+                    )
+                    .unwrap();
+                }
+                writeln!(&mut extra, "    }}").unwrap();
+            }
             writeln!(&mut extra, "}}").unwrap();
         }
 
-        // Cleanups!
-        transform::sort::Sort {}.run(&mut ir).unwrap();
-        transform::Sanitize {}.run(&mut ir).unwrap();
+        // ==============================
+        // Per instance SYSCTL, interrupt and DMAMUX consts
+        {
+            writeln!(&mut extra, "pub mod info {{").unwrap();
+            writeln!(
+                &mut extra,
+                "    //! SYSCTL resource and clock, interrupts and DMAMUX requests of each peripheral instance"
+            )
+            .unwrap();
+            for p in &core.peripherals {
+                let mut consts = Vec::new();
+                if let Some(sysctl) = &p.sysctl {
+                    consts.push(("RESOURCE".to_string(), "usize", sysctl.resource.to_string()));
+                    if let Some(clock_node) = sysctl.clock_node {
+                        consts.push(("CLOCK_NODE".to_string(), "usize", clock_node.to_string()));
+                    }
+                    if let Some(top) = sysctl.resource_clock_top {
+                        consts.push(("RESOURCE_CLOCK_TOP".to_string(), "usize", top.to_string()));
+                    }
+                    consts.push((
+                        "GROUP_LINK".to_string(),
+                        "usize",
+                        sysctl.group_link.to_string(),
+                    ));
+                    consts.push((
+                        "GROUP_BIT".to_string(),
+                        "usize",
+                        sysctl.group_bit_offset.to_string(),
+                    ));
+                }
+                // GLOBAL => IRQ, others => IRQ_<SIGNAL>
+                for irq in &p.interrupts {
+                    consts.push((
+                        signal_const_name("IRQ", &irq.signal),
+                        "crate::Interrupt",
+                        format!("crate::Interrupt::{}", irq.interrupt),
+                    ));
+                }
+                // DMA_<SIGNAL>, the requests of the chip's one DMAMUX
+                for ch in &p.dma_channels {
+                    if let Some(request) = ch.request {
+                        consts.push((
+                            signal_const_name("DMA", &ch.signal),
+                            "usize",
+                            request.to_string(),
+                        ));
+                    }
+                }
+                if consts.is_empty() {
+                    continue;
+                }
+
+                writeln!(&mut extra, "    pub mod {} {{", p.name).unwrap();
+                let mut names = HashSet::new();
+                for (name, ty, value) in consts {
+                    if !names.insert(name.clone()) {
+                        panic!("{}: more than one info::{}::{}", chip.name, p.name, name);
+                    }
+                    writeln!(
+                        &mut extra,
+                        "        pub const {}: {} = {};",
+                        name, ty, value
+                    )
+                    .unwrap();
+                }
+                writeln!(&mut extra, "    }}").unwrap();
+            }
+            writeln!(&mut extra, "}}").unwrap();
+        }
+
+        // ==============================
+        // Generate callback macros
+        {
+            let mut peripherals = Vec::new();
+            let mut dma_requests = Vec::new();
+            for p in &core.peripherals {
+                let Some(registers) = &p.registers else {
+                    continue;
+                };
+                peripherals.push(vec![
+                    p.name.clone(),
+                    registers.kind.clone(),
+                    registers.version.clone(),
+                ]);
+                for ch in &p.dma_channels {
+                    if let (Some(dmamux), Some(request)) = (&ch.dmamux, ch.request) {
+                        dma_requests.push(vec![
+                            p.name.clone(),
+                            registers.kind.clone(),
+                            ch.signal.clone(),
+                            dmamux.clone(),
+                            request.to_string(),
+                        ]);
+                    }
+                }
+            }
+            let interrupts: Vec<Vec<String>> = core
+                .interrupts
+                .iter()
+                .map(|irq| vec![irq.name.clone(), irq.number.to_string()])
+                .collect();
+            let dma_channels: Vec<Vec<String>> = core
+                .dma_channels
+                .iter()
+                .map(|ch| {
+                    vec![
+                        ch.name.clone(),
+                        ch.dma.clone(),
+                        ch.channel.to_string(),
+                        ch.dmamux_channel.to_string(),
+                    ]
+                })
+                .collect();
+
+            // (UART0, uart, v53)
+            make_table(&mut extra, "foreach_peripheral", &peripherals);
+            // (UART0, 37)
+            make_table(&mut extra, "foreach_interrupt", &interrupts);
+            // (HDMA_CH0, HDMA, 0, 0)
+            make_table(&mut extra, "foreach_dma_channel", &dma_channels);
+            // (UART0, uart, RX, DMAMUX, 8)
+            make_table(&mut extra, "foreach_dma_request", &dma_requests);
+        }
 
         // ==============================
         // Setup chip dir
@@ -227,34 +352,108 @@ impl Gen {
         // ==============================
         // generate pac.rs
 
-        let data = generate::render(&ir, &gen_opts()).unwrap().to_string();
-        let data = data.replace("] ", "]\n");
-        // FIXME: conversion
-        let data = data.replace("pub use cortex_m_rt :: interrupt ;", "");
-        let data = data.replace("cortex_m :: interrupt ", "crate ");
-        let data = data.replace("cortex_m", "riscv"); // FIXME
-
-        // avoid collision with riscv-rt
-        // In AndeStar V5 RISC-V core, the interrupt can be vectored.
-        // The first item is core trap handler.
-        let data = data.replace("__INTERRUPTS", "__VECTORED_INTERRUPTS");
-
-        // Remove inner attributes like #![no_std]
-        let data = Regex::new("# *! *\\[.*\\]").unwrap().replace_all(&data, "");
+        let blocks = block_paths(core);
+        let data = gen_device(chip, core, &blocks);
 
         let mut file = File::create(chip_dir.join("pac.rs")).unwrap();
         file.write_all(data.as_bytes()).unwrap();
         file.write_all(extra.as_bytes()).unwrap();
 
-        let mut device_x = String::new();
+        // Handlers default to `DefaultHandler`, slot 0 of the vector table to the trap entry
+        let mut device_x = String::from("PROVIDE(CORE_LOCAL = _start_trap);\n");
 
         for irq in &core.interrupts {
             writeln!(&mut device_x, "PROVIDE({} = DefaultHandler);", irq.name).unwrap();
         }
 
         // ==============================
-        // generate metadata.rs
+        // generate pins.rs and metadata.rs
+
+        fs::write(chip_dir.join("pins.rs"), gen_pins(core, &blocks)).unwrap();
+        self.gen_metadata(
+            &chip_dir.join("metadata.rs"),
+            "../",
+            chip,
+            core,
+            &peripheral_versions,
+        );
+
+        // Package variants, only pads bonded out on the package are kept
+        for package in chip.packages.iter().filter(|p| !p.pinout.is_empty()) {
+            let package_dir = chip_dir
+                .join("packages")
+                .join(package.name.to_ascii_lowercase());
+            fs::create_dir_all(&package_dir).unwrap();
+
+            let core = filter_core_for_package(core, package);
+            fs::write(package_dir.join("pins.rs"), gen_pins(&core, &blocks)).unwrap();
+            self.gen_metadata(
+                &package_dir.join("metadata.rs"),
+                "../../../",
+                chip,
+                &core,
+                &peripheral_versions,
+            );
+        }
+
+        // ==============================
+        // generate device.x
+        File::create(chip_dir.join("device.x"))
+            .unwrap()
+            .write_all(device_x.as_bytes())
+            .unwrap();
+
+        // ==============================
+        // generate cfgs.txt, read by build.rs
+        let mut cfgs = BTreeSet::new();
+        cfgs.insert(format!("family_{}", cfg_name(&chip.family)));
+        cfgs.insert(format!("sub_family_{}", cfg_name(&chip.sub_family)));
+        for p in &core.peripherals {
+            cfgs.insert(format!("peri_{}", cfg_name(&p.name)));
+        }
+        for (module, version) in &peripheral_versions {
+            cfgs.insert(format!("{}_{}", module, version));
+        }
+        // HPM_IP_FEATURE_UART_RX_IDLE_DETECT => ip_feature_uart_rx_idle_detect
+        for feature in core
+            .features
+            .iter()
+            .chain(core.peripherals.iter().flat_map(|p| &p.features))
+        {
+            cfgs.insert(format!("ip_feature_{}", feature.to_ascii_lowercase()));
+        }
+        let mut cfgs_txt = String::new();
+        for cfg in &cfgs {
+            writeln!(&mut cfgs_txt, "{}", cfg).unwrap();
+        }
+        fs::write(chip_dir.join("cfgs.txt"), cfgs_txt).unwrap();
+        self.all_cfgs.extend(cfgs);
+
+        // ==============================
+        // generate boot_header.x
+        if let Some(boot) = &chip.boot {
+            fs::write(chip_dir.join("boot_header.x"), gen_boot_header_x(boot)).unwrap();
+        }
+
+        // ==============================
+        // generate memory.x for each linker layout
+        let has_femc = core.peripherals.iter().any(|p| p.name == "FEMC");
+        gen_memory_x(
+            &chip_dir,
+            &chip.core_memory(core),
+            has_femc,
+            chip.boot.as_ref(),
+        );
+    }
 
+    fn gen_metadata(
+        &mut self,
+        path: &Path,
+        include_prefix: &str,
+        chip: &Chip,
+        core: &Core,
+        peripheral_versions: &BTreeMap<String, String>,
+    ) {
         // (peripherals, interrupts, dma_channels) are often equal across multiple chips.
         // To reduce bloat, deduplicate them.
         let mut data = String::new();
@@ -268,6 +467,9 @@ impl Gen {
                 pub(crate) static CLOCKS: &[Clock] = {};
                 pub(crate) static PINS: &[IoPin] = {};
                 pub(crate) static TRGMMUX: &[TrgmMux] = {};
+                pub(crate) static CLOCK_TREE: Option<&ClockTree> = {};
+                pub(crate) static PARAMS: &[Param] = {};
+                pub(crate) static FEATURES: &[&str] = {};
             ",
             stringify(&core.peripherals),
             stringify(&core.interrupts),
@@ -276,6 +478,12 @@ impl Gen {
             stringify(&core.clocks),
             stringify(&core.pins),
             stringify(&core.trgmmuxes),
+            match &core.clock_tree {
+                Some(clock_tree) => format!("Some(&{})", stringify(clock_tree)),
+                None => "None".to_string(),
+            },
+            stringify(params(&core.params)),
+            stringify(&core.features),
         )
         .unwrap();
 
@@ -285,7 +493,7 @@ impl Gen {
             let ir_regex = Regex::new("\":ir_for:([a-z0-9]+):\"").unwrap();
             let mut data = ir_regex.replace_all(&data, "&$1::REGISTERS").to_string();
 
-            for (module, version) in &peripheral_versions {
+            for (module, version) in peripheral_versions {
                 writeln!(
                     &mut data,
                     "#[path=\"../registers/{}_{}.rs\"] pub mod {};",
@@ -302,11 +510,14 @@ impl Gen {
         });
 
         let data = format!(
-            "include!(\"../{}\");
+            "include!(\"{}{}\");
             pub static METADATA: Metadata = Metadata {{
                 name: {:?},
                 family: {:?},
+                core: {:?},
+                hart_id: {},
                 memory: {},
+                packages: {},
                 peripherals: PERIPHERALS,
                 interrupts: INTERRUPTS,
                 dma_channels: DMA_CHANNELS,
@@ -314,26 +525,22 @@ impl Gen {
                 clocks: CLOCKS,
                 pins: PINS,
                 trgmmux: TRGMMUX,
+                clock_tree: CLOCK_TREE,
+                params: PARAMS,
+                features: FEATURES,
             }};",
+            include_prefix,
             deduped_file,
             &chip.name,
             &chip.family,
-            stringify(&chip.memory),
+            &core.name,
+            core.hart_id,
+            stringify(chip.core_memory(core)),
+            stringify(&chip.packages),
         );
 
-        let mut file = File::create(chip_dir.join("metadata.rs")).unwrap();
+        let mut file = File::create(path).unwrap();
         file.write_all(data.as_bytes()).unwrap();
-
-        // ==============================
-        // generate device.x
-        File::create(chip_dir.join("device.x"))
-            .unwrap()
-            .write_all(device_x.as_bytes())
-            .unwrap();
-
-        // ==============================
-        // generate default memory.x
-        gen_memory_x(&chip_dir, chip);
     }
 
     fn load_chip(&mut self, name: &str) -> Chip {
@@ -352,6 +559,7 @@ impl Gen {
         fs::create_dir_all(self.opts.out_dir.join("src/chips")).unwrap();
 
         let mut chip_core_names: Vec<String> = Vec::new();
+        let mut package_features: Vec<(String, Vec<String>)> = Vec::new();
 
         for chip_name in &self.opts.chips.clone() {
             println!("Generate Chip {}", chip_name);
@@ -384,6 +592,15 @@ impl Gen {
                 chip_core_names.push(chip_core_name.clone());
                 self.gen_chip(&chip_core_name, &chip, core, core_index)
             }
+
+            for package in chip.packages.iter().filter(|p| !p.pinout.is_empty()) {
+                // For multi-core chips, the core feature must be selected as well
+                let implied = match chip.cores.len() {
+                    1 => vec![chip_name.clone()],
+                    _ => vec![],
+                };
+                package_features.push((package.name.clone(), implied));
+            }
         }
 
         for (module, version) in &self.all_peripheral_versions {
@@ -393,26 +610,13 @@ impl Gen {
                 .join("registers")
                 .join(&format!("{}_{}.json", module, version));
 
-            let mut ir: ir::IR = serde_json::from_reader(
+            let json: serde_json::Value = serde_json::from_reader(
                 File::open(&regs_path).expect(&format!("open {}", regs_path.display())),
             )
             .unwrap();
+            let (chiptool_ir, ir) = registers_ir(json);
 
-            transform::expand_extends::ExpandExtends {}
-                .run(&mut ir)
-                .unwrap();
-
-            transform::map_names(&mut ir, |k, s| match k {
-                transform::NameKind::Block => *s = s.to_string(),
-                transform::NameKind::Fieldset => *s = format!("regs::{}", s),
-                transform::NameKind::Enum => *s = format!("vals::{}", s),
-                _ => {}
-            });
-
-            transform::sort::Sort {}.run(&mut ir).unwrap();
-            transform::Sanitize {}.run(&mut ir).unwrap();
-
-            let items = generate::render(&ir, &gen_opts()).unwrap();
+            let items = generate::render(&chiptool_ir, &gen_opts()).unwrap();
             let mut file = File::create(
                 self.opts
                     .out_dir
@@ -430,14 +634,13 @@ impl Gen {
             )
             .unwrap();
 
+            let items = fieldset_impls(items, &ir);
             let data = items.to_string().replace("] ", "]\n");
 
             // Remove inner attributes like #![no_std]
             let re = Regex::new("# *! *\\[.*\\]").unwrap();
             let data = re.replace_all(&data, "");
             file.write_all(data.as_bytes()).unwrap();
-
-            let ir = crate::data::ir::IR::from_chiptool(ir);
             let mut data = String::new();
 
             write!(
@@ -460,11 +663,32 @@ impl Gen {
             file.write_all(data.as_bytes()).unwrap();
         }
 
+        // All cfgs of all chips, for rustc-check-cfg
+        let mut all_cfgs = String::new();
+        for cfg in &self.all_cfgs {
+            writeln!(&mut all_cfgs, "{}", cfg).unwrap();
+        }
+        fs::write(self.opts.out_dir.join("src/chips/all_cfgs.txt"), all_cfgs).unwrap();
+
         // Generate Cargo.toml
         let mut contents = include_bytes!("../res/Cargo.toml").to_vec();
         for name in &chip_core_names {
             writeln!(&mut contents, "{} = []", name.to_ascii_lowercase()).unwrap();
         }
+        writeln!(&mut contents, "\n# Package-selection features, optional").unwrap();
+        for (name, implied) in &package_features {
+            let implied: Vec<_> = implied
+                .iter()
+                .map(|f| format!("{:?}", f.to_ascii_lowercase()))
+                .collect();
+            writeln!(
+                &mut contents,
+                "{} = [{}]",
+                name.to_ascii_lowercase(),
+                implied.join(", ")
+            )
+            .unwrap();
+        }
         fs::write(self.opts.out_dir.join("Cargo.toml"), contents).unwrap();
 
         // copy misc files
@@ -480,7 +704,7 @@ impl Gen {
         .unwrap();
         fs::write(
             self.opts.out_dir.join("src/common.rs"),
-            chiptool::generate::COMMON_MODULE,
+            include_bytes!("../res/src/common.rs"),
         )
         .unwrap();
         fs::write(
@@ -488,7 +712,270 @@ impl Gen {
             include_bytes!("../res/src/metadata.rs"),
         )
         .unwrap();
+        fs::write(
+            self.opts.out_dir.join("src/boot_header.rs"),
+            include_bytes!("../res/src/boot_header.rs"),
+        )
+        .unwrap();
+    }
+}
+
+// "HPM6700/6400 Series" => hpm6700_6400, "HPM6200, Dual-core" => hpm6200_dual_core
+fn cfg_name(name: &str) -> String {
+    let name = name.trim_end_matches(" Series").to_ascii_lowercase();
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// The chiptool IR of build/data/registers ready to render, and the IR of the metadata
+fn registers_ir(json: serde_json::Value) -> (ir::IR, data::ir::IR) {
+    let extras = extras(&json);
+    let mut ir: ir::IR = serde_json::from_value(json).unwrap();
+
+    transform::expand_extends::ExpandExtends {}
+        .run(&mut ir)
+        .unwrap();
+
+    let extras = rename_extras(&ir, extras);
+    rename(&mut ir);
+
+    let data_ir = data::ir::IR::from_chiptool(ir.clone(), &extras);
+    (ir, data_ir)
+}
+
+/// Names of the generated code, fieldsets in `mod regs`, enums in `mod vals`, Rust casing
+fn rename(ir: &mut ir::IR) {
+    transform::map_names(ir, |k, s| match k {
+        transform::NameKind::Block => *s = s.to_string(),
+        transform::NameKind::Fieldset => *s = format!("regs::{}", s),
+        transform::NameKind::Enum => *s = format!("vals::{}", s),
+        _ => {}
+    });
+
+    transform::sort::Sort {}.run(ir).unwrap();
+    transform::Sanitize {}.run(ir).unwrap();
+}
+
+/// `extras` keyed by the names `rename` gives. The YAML names go through `rename` as the
+/// descriptions of a copy of `ir`.
+fn rename_extras(ir: &ir::IR, extras: data::ir::Extras) -> data::ir::Extras {
+    let mut names = ir.clone();
+    for (name, block) in &mut names.blocks {
+        block.description = Some(name.clone());
+        for item in &mut block.items {
+            item.description = Some(item.name.clone());
+        }
+    }
+    for (name, fieldset) in &mut names.fieldsets {
+        fieldset.description = Some(name.clone());
+        for field in &mut fieldset.fields {
+            field.description = Some(field.name.clone());
+        }
+    }
+    rename(&mut names);
+
+    let mut renamed = data::ir::Extras::default();
+    for (name, block) in &names.blocks {
+        let block_name = block.description.clone().unwrap();
+        for item in &block.items {
+            let key = (block_name.clone(), item.description.clone().unwrap());
+            if let Some(v) = extras.reset_values.get(&key) {
+                let key = (name.clone(), item.name.clone());
+                renamed.reset_values.insert(key, *v);
+            }
+        }
+    }
+    for (name, fieldset) in &names.fieldsets {
+        let fieldset_name = fieldset.description.clone().unwrap();
+        let name = name.strip_prefix("regs::").unwrap();
+        for field in &fieldset.fields {
+            let key = (fieldset_name.clone(), field.description.clone().unwrap());
+            let renamed_key = (name.to_string(), field.name.clone());
+            if let Some(v) = extras.modified_write_values.get(&key) {
+                renamed
+                    .modified_write_values
+                    .insert(renamed_key.clone(), *v);
+            }
+            if let Some(v) = extras.read_actions.get(&key) {
+                renamed.read_actions.insert(renamed_key, *v);
+            }
+        }
+    }
+
+    assert_eq!(
+        (
+            extras.reset_values.len(),
+            extras.modified_write_values.len(),
+            extras.read_actions.len()
+        ),
+        (
+            renamed.reset_values.len(),
+            renamed.modified_write_values.len(),
+            renamed.read_actions.len()
+        ),
+        "reset_value, modified_write_values or read_action of an item or field that does not exist"
+    );
+    renamed
+}
+
+/// `reset_value` of block items, `modified_write_values` and `read_action` of fieldset fields
+/// in build/data/registers
+fn extras(json: &serde_json::Value) -> data::ir::Extras {
+    let mut extras = data::ir::Extras::default();
+    let Some(objects) = json.as_object() else {
+        return extras;
+    };
+    for (key, object) in objects {
+        if let Some(block_name) = key.strip_prefix("block/") {
+            for item in object["items"].as_array().into_iter().flatten() {
+                if let (Some(name), Some(reset_value)) =
+                    (item["name"].as_str(), item["reset_value"].as_u64())
+                {
+                    extras
+                        .reset_values
+                        .insert((block_name.to_string(), name.to_string()), reset_value);
+                }
+            }
+        } else if let Some(fieldset_name) = key.strip_prefix("fieldset/") {
+            for field in object["fields"].as_array().into_iter().flatten() {
+                let Some(name) = field["name"].as_str() else {
+                    continue;
+                };
+                let key = (fieldset_name.to_string(), name.to_string());
+                if let Some(v) = field.get("modified_write_values") {
+                    let v = serde_json::from_value(v.clone())
+                        .unwrap_or_else(|e| panic!("{fieldset_name}.{name}: {e}"));
+                    extras.modified_write_values.insert(key.clone(), v);
+                }
+                if let Some(v) = field.get("read_action") {
+                    let v = serde_json::from_value(v.clone())
+                        .unwrap_or_else(|e| panic!("{fieldset_name}.{name}: {e}"));
+                    extras.read_actions.insert(key, v);
+                }
+            }
+        }
     }
+    extras
+}
+
+/// The write-one-to-clear bits of a fieldset
+fn w1c_mask(fieldset: &data::ir::FieldSet) -> u64 {
+    let mut mask = 0u64;
+    for field in &fieldset.fields {
+        if field.modified_write_values != Some(data::ir::ModifiedWriteValues::OneToClear) {
+            continue;
+        }
+        let data::ir::BitOffset::Regular(offset) = &field.bit_offset else {
+            continue;
+        };
+        let offsets = match &field.array {
+            None => vec![offset.offset],
+            Some(data::ir::Array::Regular(a)) => {
+                (0..a.len).map(|i| offset.offset + i * a.stride).collect()
+            }
+            Some(data::ir::Array::Cursed(a)) => {
+                a.offsets.iter().map(|o| offset.offset + o).collect()
+            }
+        };
+        let field_mask = (1u64 << field.bit_size) - 1;
+        for o in offsets {
+            mask |= field_mask << o;
+        }
+    }
+    mask
+}
+
+/// chiptool fieldsets default to zero, their `impl Default` in `mod regs` is replaced by one
+/// with the reset value, when every register of the fieldset has the same one. The reset value
+/// and the read value of `modify` have the write-one-to-clear bits zeroed, by the
+/// `common::WriteOneToClear` impl of every fieldset.
+fn fieldset_impls(items: TokenStream, ir: &data::ir::IR) -> TokenStream {
+    let mut fieldset_resets: BTreeMap<&str, BTreeSet<u64>> = BTreeMap::new();
+    for block in &ir.blocks {
+        for item in &block.items {
+            if let data::ir::BlockItemInner::Register(register) = &item.inner {
+                if let Some(fieldset) = &register.fieldset {
+                    fieldset_resets
+                        .entry(fieldset)
+                        .or_default()
+                        .insert(register.reset_value.unwrap_or(0));
+                }
+            }
+        }
+    }
+    let w1c_masks: BTreeMap<&str, u64> = ir
+        .fieldsets
+        .iter()
+        .map(|f| (f.name.as_str(), w1c_mask(f)))
+        .collect();
+
+    let mut defaults: BTreeMap<&str, TokenStream> = BTreeMap::new();
+    for (fieldset, resets) in fieldset_resets {
+        if let [reset_value] = resets.into_iter().collect::<Vec<_>>()[..] {
+            let reset_value = reset_value & !w1c_masks[fieldset];
+            if reset_value != 0 {
+                let default = format!(
+                    "impl Default for {fieldset} {{
+                        #[inline(always)]
+                        fn default() -> {fieldset} {{
+                            {fieldset}(0x{reset_value:x})
+                        }}
+                    }}"
+                );
+                defaults.insert(fieldset, default.parse().unwrap());
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut tokens = items.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        // only in mod regs, mod vals can have a type of the same name
+        let is_regs = matches!(&token, TokenTree::Ident(i) if i == "mod")
+            && matches!(tokens.peek(), Some(TokenTree::Ident(i)) if i == "regs");
+        out.push(token);
+        if !is_regs {
+            continue;
+        }
+        out.extend(tokens.next());
+        let Some(TokenTree::Group(regs)) = tokens.next() else {
+            panic!("mod regs without a body");
+        };
+        let mut body: Vec<TokenTree> = regs.stream().into_iter().collect();
+        for (fieldset, default) in &defaults {
+            // impl Default for X { ... }
+            let i = body
+                .windows(5)
+                .position(|w| match w {
+                    [TokenTree::Ident(i), TokenTree::Ident(d), TokenTree::Ident(f), TokenTree::Ident(n), TokenTree::Group(_)] => {
+                        i == "impl" && d == "Default" && f == "for" && n == fieldset
+                    }
+                    _ => false,
+                })
+                .unwrap_or_else(|| panic!("no Default of regs::{fieldset} in the chiptool output"));
+            body.splice(i..i + 5, default.clone());
+        }
+        for (fieldset, mask) in &w1c_masks {
+            let w1c = match mask {
+                0 => format!("impl crate::common::WriteOneToClear for {fieldset} {{}}"),
+                _ => format!(
+                    "impl crate::common::WriteOneToClear for {fieldset} {{
+                        #[inline(always)]
+                        fn without_w1c(self) -> {fieldset} {{
+                            {fieldset}(self.0 & !0x{mask:x})
+                        }}
+                    }}"
+                ),
+            };
+            body.extend(w1c.parse::<TokenStream>().unwrap());
+        }
+        let mut group = Group::new(regs.delimiter(), body.into_iter().collect());
+        group.set_span(regs.span());
+        out.push(TokenTree::Group(group));
+    }
+    out.into_iter().collect()
 }
 
 fn stringify<T: Debug>(metadata: T) -> String {
@@ -500,125 +987,1180 @@ fn stringify<T: Debug>(metadata: T) -> String {
     metadata.replace(": [", ": &[")
 }
 
+/// Standard machine mode interrupt codes of the privileged spec
+const CORE_INTERRUPTS: &[(&str, usize)] = &[
+    ("MachineSoft", 3),
+    ("MachineTimer", 7),
+    ("MachineExternal", 11),
+];
+
+/// Standard exception codes of the privileged spec
+const EXCEPTIONS: &[(&str, usize)] = &[
+    ("InstructionMisaligned", 0),
+    ("InstructionFault", 1),
+    ("IllegalInstruction", 2),
+    ("Breakpoint", 3),
+    ("LoadMisaligned", 4),
+    ("LoadFault", 5),
+    ("StoreMisaligned", 6),
+    ("StoreFault", 7),
+    ("UserEnvCall", 8),
+    ("SupervisorEnvCall", 9),
+    ("MachineEnvCall", 11),
+    ("InstructionPageFault", 12),
+    ("LoadPageFault", 13),
+    ("StorePageFault", 15),
+];
+
+/// An enum with a `riscv_pac` number trait, `(trait, max const)`, and marker traits
+fn gen_number_enum(
+    out: &mut String,
+    doc: &str,
+    name: &str,
+    variants: &[(&str, usize)],
+    (number_trait, max_const): (&str, &str),
+    marker_traits: &[&str],
+) {
+    writeln!(
+        out,
+        "/// {doc}
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        #[repr(u16)]
+        pub enum {name} {{"
+    )
+    .unwrap();
+    for (variant, number) in variants {
+        writeln!(out, "    {} = {},", variant, number).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    let max = variants
+        .iter()
+        .map(|(_, number)| *number)
+        .max()
+        .unwrap_or(0);
+    writeln!(
+        out,
+        "unsafe impl riscv_pac::{number_trait} for {name} {{
+            const {max_const}: usize = {max};
+            #[inline]
+            fn number(self) -> usize {{
+                self as usize
+            }}
+            #[inline]
+            fn from_number(value: usize) -> riscv_pac::result::Result<Self> {{
+                match value {{"
+    )
+    .unwrap();
+    for (variant, number) in variants {
+        writeln!(out, "            {} => Ok(Self::{}),", number, variant).unwrap();
+    }
+    writeln!(
+        out,
+        "            _ => Err(riscv_pac::result::Error::InvalidVariant(value)),
+                }}
+            }}
+        }}"
+    )
+    .unwrap();
+    for marker in marker_traits {
+        writeln!(out, "unsafe impl riscv_pac::{} for {} {{}}", marker, name).unwrap();
+    }
+}
+
+/// `TARGETCONFIG[0].CLAIM` of PLIC, and the stride of `TARGETCONFIG`, from plic_common.yaml
+const PLIC_CLAIM_OFFSET: u64 = 0x20_0004;
+const PLIC_TARGET_STRIDE: u64 = 0x1000;
+
+/// Integer registers the trampolines save: a0, a1, ra, t0-t6, a2-a7 and the interrupt number
+const TRAMPOLINE_FRAME: usize = 80;
+
+/// Caller-saved float registers
+const FLOAT_REGISTERS: &[&str] = &[
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fa0", "fa1", "fa2", "fa3", "fa4",
+    "fa5", "fa6", "fa7", "ft8", "ft9", "ft10", "ft11",
+];
+
+/// Vectored mode trampoline shared by all interrupts, `a0` is the interrupt number and `a1`
+/// the handler, with a0 and a1 saved in the frame. `float` is (store, load, size) of float
+/// registers to save.
+fn gen_trampoline_common(claim: u64, float: Option<(&str, &str, usize)>) -> Vec<String> {
+    let mut lines = vec![
+        ".balign 4".to_string(),
+        ".global __hpm_vectored_common".to_string(),
+        "__hpm_vectored_common:".to_string(),
+        format!("    sw a0, {}(sp)", TRAMPOLINE_FRAME - 16),
+    ];
+    let saved: Vec<(&str, usize)> = ["ra", "t0", "t1", "t2", "t3", "t4", "t5", "t6"]
+        .into_iter()
+        .chain(["a2", "a3", "a4", "a5", "a6", "a7"])
+        .enumerate()
+        .map(|(i, r)| (r, 8 + 4 * i))
+        .collect();
+    for (r, offset) in &saved {
+        lines.push(format!("    sw {}, {}(sp)", r, offset));
+    }
+    if let Some((store, _, size)) = float {
+        lines.push(format!(
+            "    addi sp, sp, -{}",
+            FLOAT_REGISTERS.len() * size
+        ));
+        for (i, r) in FLOAT_REGISTERS.iter().enumerate() {
+            lines.push(format!("    {} {}, {}(sp)", store, r, i * size));
+        }
+    }
+    lines.push("    jalr a1".to_string());
+    if let Some((_, load, size)) = float {
+        for (i, r) in FLOAT_REGISTERS.iter().enumerate() {
+            lines.push(format!("    {} {}, {}(sp)", load, r, i * size));
+        }
+        lines.push(format!("    addi sp, sp, {}", FLOAT_REGISTERS.len() * size));
+    }
+    // complete the source the PLIC claimed when vectoring
+    lines.push(format!("    lw t0, {}(sp)", TRAMPOLINE_FRAME - 16));
+    lines.push(format!("    li t1, 0x{:x}", claim));
+    lines.push("    sw t0, 0(t1)".to_string());
+    for (r, offset) in &saved {
+        lines.push(format!("    lw {}, {}(sp)", r, offset));
+    }
+    lines.push("    lw a0, 0(sp)".to_string());
+    lines.push("    lw a1, 4(sp)".to_string());
+    lines.push(format!("    addi sp, sp, {}", TRAMPOLINE_FRAME));
+    lines.push("    mret".to_string());
+    lines
+}
+
+fn write_global_asm(out: &mut String, cfg: Option<&str>, lines: &[String]) {
+    if let Some(cfg) = cfg {
+        writeln!(out, "    #[cfg({})]", cfg).unwrap();
+    }
+    writeln!(out, "    core::arch::global_asm!(").unwrap();
+    writeln!(out, "        {:?},", ".section .trap, \"ax\"").unwrap();
+    for line in lines {
+        writeln!(out, "        {:?},", line).unwrap();
+    }
+    writeln!(out, "    );").unwrap();
+}
+
+/// The `rt` module: the vector table of the PLIC vectored mode with a trampoline per interrupt,
+/// and the `MachineExternal` dispatch of the non-vectored mode
+fn gen_rt(core: &Core, interrupts: &[&Interrupt]) -> String {
+    let mut out = String::new();
+
+    let plic = core
+        .peripherals
+        .iter()
+        .find(|p| p.name == "PLIC")
+        .expect("No PLIC peripheral");
+    let claim = plic.address + PLIC_CLAIM_OFFSET + PLIC_TARGET_STRIDE * core.hart_id as u64;
+
+    writeln!(
+        &mut out,
+        "#[cfg(all(feature = \"rt\", target_arch = \"riscv32\"))]
+pub mod rt {{
+    //! Interrupt runtime of the PLIC, for `riscv-rt`
+    //!
+    //! Handlers are `extern \"C\" fn()` named after the `Interrupt` variant, like
+    //! `#[no_mangle] extern \"C\" fn UART0() {{}}` or `riscv-rt`'s
+    //! `#[external_interrupt(Interrupt::UART0)]`. Link with `-Tdevice.x`, it provides
+    //! `DefaultHandler` for the handlers not defined, and `_start_trap` of `riscv-rt` for
+    //! `CORE_LOCAL`, slot 0 of the vector table.
+    //!
+    //! The same handlers serve both modes, with interrupts disabled:
+    //! - non-vectored, after reset: `riscv-rt` calls `MachineExternal`, which claims the source,
+    //!   calls its handler and completes it
+    //! - vectored, after [`enable_vectored_mode`]: the PLIC claims the source and jumps through
+    //!   `__VECTORED_INTERRUPTS` to a trampoline saving the caller-saved registers, which calls
+    //!   the handler, completes the source and returns with `mret`. Exceptions and core local
+    //!   interrupts go to `CORE_LOCAL`.
+
+    extern \"C\" {{
+        fn CORE_LOCAL();"
+    )
+    .unwrap();
+    for irq in interrupts {
+        writeln!(&mut out, "        fn {}();", irq.name).unwrap();
+        writeln!(&mut out, "        fn __hpm_vectored_{}();", irq.name).unwrap();
+    }
+    writeln!(&mut out, "    }}").unwrap();
+
+    // slot 0 is CORE_LOCAL, unused numbers are reserved
+    let len = interrupts.last().map_or(0, |irq| irq.number as usize) + 1;
+    let mut vectors = vec!["Vector { _reserved: 0 }".to_string(); len];
+    vectors[0] = "Vector { _handler: CORE_LOCAL }".to_string();
+    for irq in interrupts {
+        vectors[irq.number as usize] =
+            format!("Vector {{ _handler: __hpm_vectored_{} }}", irq.name);
+    }
+    writeln!(
+        &mut out,
+        "
+    #[doc(hidden)]
+    pub union Vector {{
+        _handler: unsafe extern \"C\" fn(),
+        _reserved: usize,
+    }}
+
+    /// The `mtvec` base of the vectored mode, aligned like the SDK's `__vector_table`
+    #[doc(hidden)]
+    #[repr(C, align(512))]
+    pub struct VectorTable([Vector; {len}]);
+
+    /// Indexed by `Interrupt`, slot 0 is `CORE_LOCAL`
+    #[link_section = \".vector_table.interrupts\"]
+    #[no_mangle]
+    pub static __VECTORED_INTERRUPTS: VectorTable = VectorTable([
+        {vectors}
+    ]);
+
+    const PLIC_FEATURE: *mut u32 = 0x{plic:x}usize as _;
+    /// `TARGETCONFIG[HART_ID].CLAIM`
+    const PLIC_CLAIM: *mut u32 = 0x{claim:x}usize as _;
+
+    /// Non-vectored mode dispatch, called by `riscv-rt` for the machine external interrupt
+    #[no_mangle]
+    unsafe extern \"C\" fn MachineExternal() {{
+        let id = PLIC_CLAIM.read_volatile();
+        match id {{",
+        len = len,
+        vectors = vectors.join(",\n        "),
+        plic = plic.address,
+        claim = claim,
+    )
+    .unwrap();
+    for irq in interrupts {
+        writeln!(&mut out, "            {} => {}(),", irq.number, irq.name).unwrap();
+    }
+    writeln!(
+        &mut out,
+        "            _ => {{}}
+        }}
+        // 0 is no interrupt pending
+        if id != 0 {{
+            PLIC_CLAIM.write_volatile(id);
+        }}
+    }}
+
+    /// Switch the PLIC to vectored mode and point `mtvec` of this hart at `__VECTORED_INTERRUPTS`.
+    ///
+    /// # Safety
+    ///
+    /// Call with interrupts disabled. `PLIC.FEATURE` is shared by the harts, all of them must
+    /// switch. The `mtvec` set up by `riscv-rt` is replaced, `CORE_LOCAL` takes over its traps.
+    pub unsafe fn enable_vectored_mode() {{
+        PLIC_FEATURE.write_volatile(PLIC_FEATURE.read_volatile() | 1 << 1);
+        // VEC_PLIC of the Andes `mmisc_ctl`
+        core::arch::asm!(\"csrsi 0x7d0, 2\");
+        core::arch::asm!(\"csrw mtvec, {{0}}\", in(reg) &__VECTORED_INTERRUPTS as *const VectorTable as usize);
+    }}
+"
+    )
+    .unwrap();
+
+    // a stub per interrupt saves a0 and a1 to pass the number and the handler
+    let mut stubs = Vec::new();
+    for irq in interrupts {
+        stubs.extend([
+            ".balign 4".to_string(),
+            format!(".global __hpm_vectored_{}", irq.name),
+            format!("__hpm_vectored_{}:", irq.name),
+            format!("    addi sp, sp, -{}", TRAMPOLINE_FRAME),
+            "    sw a0, 0(sp)".to_string(),
+            "    sw a1, 4(sp)".to_string(),
+            format!("    li a0, {}", irq.number),
+            format!("    la a1, {}", irq.name),
+            "    j __hpm_vectored_common".to_string(),
+        ]);
+    }
+    write_global_asm(&mut out, None, &stubs);
+    write_global_asm(
+        &mut out,
+        Some("target_feature = \"d\""),
+        &gen_trampoline_common(claim, Some(("fsd", "fld", 8))),
+    );
+    write_global_asm(
+        &mut out,
+        Some("all(target_feature = \"f\", not(target_feature = \"d\"))"),
+        &gen_trampoline_common(claim, Some(("fsw", "flw", 4))),
+    );
+    write_global_asm(
+        &mut out,
+        Some("not(target_feature = \"f\")"),
+        &gen_trampoline_common(claim, None),
+    );
+    writeln!(&mut out, "}}").unwrap();
+
+    out
+}
+
+/// Register block types of the peripherals as chiptool renders them, `UART0` => `uart::Uart`
+fn block_paths(core: &Core) -> HashMap<String, String> {
+    let peripherals: Vec<(&str, String)> = core
+        .peripherals
+        .iter()
+        .filter_map(|p| {
+            let registers = p.registers.as_ref()?;
+            Some((
+                p.name.as_str(),
+                format!("{}::{}", registers.kind, registers.block),
+            ))
+        })
+        .collect();
+    let paths = sanitize_block_paths(peripherals.iter().map(|(_, path)| path.clone()));
+
+    peripherals
+        .iter()
+        .zip(paths)
+        .map(|((name, _), path)| (name.to_string(), path))
+        .collect()
+}
+
+/// `kind::BLOCK` paths renamed the way the register blocks are in `gen`
+fn sanitize_block_paths(paths: impl Iterator<Item = String>) -> Vec<String> {
+    let mut ir = ir::IR::new();
+    let dev = ir::Device {
+        interrupts: Vec::new(),
+        peripherals: paths
+            .enumerate()
+            .map(|(i, path)| ir::Peripheral {
+                name: format!("P{}", i),
+                array: None,
+                base_address: 0,
+                block: Some(path),
+                description: None,
+                interrupts: HashMap::new(),
+            })
+            .collect(),
+        nvic_priority_bits: None,
+    };
+    ir.devices.insert("".to_string(), dev);
+    transform::Sanitize {}.run(&mut ir).unwrap();
+
+    ir.devices[""]
+        .peripherals
+        .iter()
+        .map(|p| p.block.clone().unwrap())
+        .collect()
+}
+
+/// The `hart` module, this core's contexts of the shared PLIC and its MCHTMR.
+/// Every core is target `HART_ID` of the PLIC and PLICSW.
+fn gen_hart(core: &Core, blocks: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+
+    writeln!(&mut out, "pub mod hart {{").unwrap();
+    writeln!(
+        &mut out,
+        "    //! PLIC and PLICSW target, PLIC contexts and MCHTMR of this core"
+    )
+    .unwrap();
+    writeln!(&mut out, "    pub const ID: usize = {};", core.hart_id).unwrap();
+
+    for name in ["PLIC", "PLICSW"] {
+        if blocks.contains_key(name) {
+            writeln!(&mut out, "    pub const {}_TARGET: usize = ID;", name).unwrap();
+        }
+    }
+    // `TARGETINT` (enables) and `TARGETCONFIG` (threshold, claim) are arrays indexed by target,
+    // PLICSW's registers only describe target 0
+    if let Some(plic) = core.peripherals.iter().find(|p| p.name == "PLIC") {
+        let kind = &plic.registers.as_ref().unwrap().kind;
+        let items = ["TARGETINT", "TARGETCONFIG"];
+        let paths = sanitize_block_paths(items.iter().map(|item| format!("{}::{}", kind, item)));
+        for (item, path) in items.iter().zip(paths) {
+            // not consts, offsetting a pointer made from an address is not allowed in const eval
+            writeln!(&mut out, "    #[inline(always)]").unwrap();
+            writeln!(
+                &mut out,
+                "    pub fn plic_{accessor}() -> crate::{path} {{ crate::PLIC.{accessor}(PLIC_TARGET) }}",
+                accessor = item.to_ascii_lowercase(),
+            )
+            .unwrap();
+        }
+    }
+    if let Some(mchtmr) = blocks.get("MCHTMR") {
+        writeln!(
+            &mut out,
+            "    /// Every core sees its own MCHTMR at the same address"
+        )
+        .unwrap();
+        writeln!(
+            &mut out,
+            "    pub const MCHTMR: crate::{} = crate::MCHTMR;",
+            mchtmr
+        )
+        .unwrap();
+    }
+    writeln!(&mut out, "}}").unwrap();
+
+    out
+}
+
+/// Peripheral instances, and the `Interrupt` enum of the PLIC sources with the `rt` module
+/// of the PLIC vectored mode
+fn gen_device(chip: &Chip, core: &Core, blocks: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+
+    for p in &core.peripherals {
+        match blocks.get(&p.name) {
+            Some(block) => writeln!(
+                &mut out,
+                "pub const {name}: {block} = unsafe {{ {block}::from_ptr(0x{address:08x}usize as _) }};",
+                name = p.name,
+                address = p.address,
+            ),
+            None => writeln!(
+                &mut out,
+                "pub const {}: *mut () = 0x{:08x}usize as _;",
+                p.name, p.address
+            ),
+        }
+        .unwrap();
+    }
+
+    let mut interrupts: Vec<&Interrupt> = core.interrupts.iter().collect();
+    interrupts.sort_by_key(|irq| irq.number);
+
+    let variants: Vec<(&str, usize)> = interrupts
+        .iter()
+        .map(|irq| (irq.name.as_str(), irq.number as usize))
+        .collect();
+    gen_number_enum(
+        &mut out,
+        "External interrupts, the value is the PLIC interrupt source ID",
+        "Interrupt",
+        &variants,
+        ("InterruptNumber", "MAX_INTERRUPT_NUMBER"),
+        &["ExternalInterruptNumber"],
+    );
+    gen_number_enum(
+        &mut out,
+        "Core local interrupts of machine mode, the value is the `mcause` interrupt code",
+        "CoreInterrupt",
+        CORE_INTERRUPTS,
+        ("InterruptNumber", "MAX_INTERRUPT_NUMBER"),
+        &["CoreInterruptNumber"],
+    );
+    gen_number_enum(
+        &mut out,
+        "Exceptions, the value is the `mcause` exception code",
+        "Exception",
+        EXCEPTIONS,
+        ("ExceptionNumber", "MAX_EXCEPTION_NUMBER"),
+        &[],
+    );
+    let harts: Vec<(String, usize)> = chip
+        .cores
+        .iter()
+        .map(|c| (format!("H{}", c.hart_id), c.hart_id as usize))
+        .collect();
+    let harts: Vec<(&str, usize)> = harts.iter().map(|(n, id)| (n.as_str(), *id)).collect();
+    gen_number_enum(
+        &mut out,
+        "Harts of the chip, the value is `mhartid` and the PLIC target",
+        "Hart",
+        &harts,
+        ("HartIdNumber", "MAX_HART_ID_NUMBER"),
+        &[],
+    );
+
+    out.push_str(&gen_hart(core, blocks));
+    out.push_str(&gen_rt(core, &interrupts));
+
+    out
+}
+
+fn gen_pins(core: &Core, blocks: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+
+    // All pin pads
+    writeln!(&mut out, "pub mod pins {{").unwrap();
+    writeln!(&mut out, "    //! Pin pad definitions").unwrap();
+    for pin in &core.pins {
+        writeln!(
+            &mut out,
+            "    pub const {}: usize = {};",
+            pin.name.to_ascii_uppercase(),
+            pin.index
+        )
+        .unwrap();
+    }
+    writeln!(&mut out, "}}").unwrap();
+
+    // All iomux consts
+    writeln!(&mut out, "pub mod iomux {{").unwrap();
+    writeln!(&mut out, "    //! `FUNC_CTL` function mux definitions").unwrap();
+    for mux in &core.iomuxes {
+        writeln!(
+            &mut out,
+            "    pub const {}: u8 = {};",
+            mux.name.to_ascii_uppercase(),
+            mux.value
+        )
+        .unwrap();
+    }
+    writeln!(&mut out, "}}").unwrap();
+
+    out.push_str(&gen_signals(core, blocks));
+
+    // (PA00, UART0, uart, TXD, 2), analog ones without alt: (PB08, ADC0, adc, INA0)
+    let mut pins = Vec::new();
+    let mut analog_pins = Vec::new();
+    let pads: HashSet<&str> = core.pins.iter().map(|p| p.name.as_str()).collect();
+    for p in &core.peripherals {
+        let Some(registers) = &p.registers else {
+            continue;
+        };
+        for pin in p.pins.iter().filter(|pin| pads.contains(pin.pin.as_str())) {
+            let mut row = vec![
+                pin.pin.clone(),
+                p.name.clone(),
+                registers.kind.clone(),
+                pin.signal.clone(),
+            ];
+            match pin.alt {
+                Some(alt) => {
+                    row.push(alt.to_string());
+                    pins.push(row);
+                }
+                None => analog_pins.push(row),
+            }
+        }
+    }
+    make_table(&mut out, "foreach_pin", &pins);
+    make_table(&mut out, "foreach_analog_pin", &analog_pins);
+
+    out
+}
+
+/// Callback macro over a table, each row is matched against the caller's patterns:
+///
+/// `foreach_peripheral!((UART0, uart, $version:ident) => { ... };)`
+fn make_table(out: &mut String, name: &str, data: &[Vec<String>]) {
+    write!(
+        out,
+        "#[macro_export]
+macro_rules! {name} {{
+    ($($pat:tt => $code:tt;)*) => {{
+        macro_rules! __{name}_inner {{
+            $(($pat) => $code;)*
+            ($_:tt) => {{}}
+        }}
+"
+    )
+    .unwrap();
+    for row in data {
+        writeln!(out, "        __{name}_inner!(({}));", row.join(",")).unwrap();
+    }
+    writeln!(out, "    }};\n}}").unwrap();
+}
+
+/// (peripheral, pin) => alts of a signal
+type SignalPins<'a> = BTreeMap<(&'a str, &'a str), Vec<u8>>;
+
+/// signal => (peripheral, pin) of the analog signals
+type AnalogPins<'a> = BTreeMap<&'a str, BTreeSet<(&'a str, &'a str)>>;
+
+/// Zero-sized pad and peripheral types, with one marker trait per peripheral signal.
+///
+/// `impl uart::TxdPin<peripherals::UART0> for pads::PA00 { const ALT: u8 = 2; }`, the peripheral
+/// types implement `Peripheral`, whose `REGS` is the register block of the instance, `crate::UART0`.
+/// Analog signals have no alt, their traits have no `ALT`: `impl adc::Ina0Pin<peripherals::ADC0> for pads::PB08 {}`.
+fn gen_signals(core: &Core, blocks: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+
+    let pads: HashSet<&str> = core.pins.iter().map(|p| p.name.as_str()).collect();
+
+    // kind => signal => (peripheral, pin) => [alt]
+    let mut kinds: BTreeMap<&str, BTreeMap<&str, SignalPins>> = BTreeMap::new();
+    // kind => signal => (peripheral, pin), analog signals have no alt
+    let mut analogs: BTreeMap<&str, AnalogPins> = BTreeMap::new();
+    // peripheral => block
+    let mut peripherals: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut used_pads: BTreeSet<&str> = BTreeSet::new();
+    for p in &core.peripherals {
+        let Some(registers) = &p.registers else {
+            continue;
+        };
+        for pin in &p.pins {
+            if !pads.contains(pin.pin.as_str()) {
+                continue;
+            }
+            match pin.alt {
+                Some(alt) => kinds
+                    .entry(&registers.kind)
+                    .or_default()
+                    .entry(&pin.signal)
+                    .or_default()
+                    .entry((p.name.as_str(), pin.pin.as_str()))
+                    .or_default()
+                    .push(alt),
+                None => {
+                    analogs
+                        .entry(&registers.kind)
+                        .or_default()
+                        .entry(&pin.signal)
+                        .or_default()
+                        .insert((p.name.as_str(), pin.pin.as_str()));
+                }
+            }
+            peripherals.insert(&p.name, &blocks[&p.name]);
+            used_pads.insert(&pin.pin);
+        }
+    }
+
+    writeln!(&mut out, "pub mod signals {{").unwrap();
+    writeln!(
+        &mut out,
+        "    //! Typed pin and alternate function constraints of peripheral signals"
+    )
+    .unwrap();
+    writeln!(&mut out, "    pub trait Pad {{ const PAD: usize; }}").unwrap();
+    writeln!(
+        &mut out,
+        "    pub trait Peripheral {{ type Regs; const REGS: Self::Regs; }}"
+    )
+    .unwrap();
+
+    writeln!(&mut out, "    pub mod pads {{").unwrap();
+    for pad in &used_pads {
+        writeln!(&mut out, "        pub struct {pad};").unwrap();
+        writeln!(
+            &mut out,
+            "        impl super::Pad for {pad} {{ const PAD: usize = crate::pins::{pad}; }}"
+        )
+        .unwrap();
+    }
+    writeln!(&mut out, "    }}").unwrap();
+
+    writeln!(&mut out, "    pub mod peripherals {{").unwrap();
+    for (p, block) in &peripherals {
+        writeln!(&mut out, "        pub struct {p};").unwrap();
+        writeln!(
+            &mut out,
+            "        impl super::Peripheral for {p} {{ type Regs = crate::{block}; const REGS: Self::Regs = crate::{p}; }}"
+        )
+        .unwrap();
+    }
+    writeln!(&mut out, "    }}").unwrap();
+
+    let kind_names: BTreeSet<&str> = kinds.keys().chain(analogs.keys()).copied().collect();
+    for kind in kind_names {
+        writeln!(&mut out, "    pub mod {kind} {{").unwrap();
+        for (signal, pins) in kinds.get(kind).into_iter().flatten() {
+            let trait_name = signal_trait_name(signal);
+            // `ALTS` lists every alt when the signal is routed to the pad more than once
+            writeln!(
+                &mut out,
+                "        pub trait {trait_name}<T: super::Peripheral>: super::Pad {{ const ALT: u8; const ALTS: &'static [u8] = &[Self::ALT]; }}"
+            )
+            .unwrap();
+            for ((periph, pad), alts) in pins {
+                let alts_const = match alts.len() {
+                    1 => String::new(),
+                    _ => format!(" const ALTS: &'static [u8] = &{:?};", alts),
+                };
+                writeln!(
+                    &mut out,
+                    "        impl {trait_name}<super::peripherals::{periph}> for super::pads::{pad} {{ const ALT: u8 = {};{alts_const} }}",
+                    alts[0]
+                )
+                .unwrap();
+            }
+        }
+        // analog functions are not selected by `FUNC_CTL`, the trait only marks the pads
+        for (signal, pins) in analogs.get(kind).into_iter().flatten() {
+            assert!(
+                !kinds.get(kind).is_some_and(|s| s.contains_key(signal)),
+                "{}: signal {} has pins with and without alt",
+                kind,
+                signal
+            );
+            let trait_name = signal_trait_name(signal);
+            writeln!(
+                &mut out,
+                "        pub trait {trait_name}<T: super::Peripheral>: super::Pad {{}}"
+            )
+            .unwrap();
+            for (periph, pad) in pins {
+                writeln!(
+                    &mut out,
+                    "        impl {trait_name}<super::peripherals::{periph}> for super::pads::{pad} {{}}"
+                )
+                .unwrap();
+            }
+        }
+        writeln!(&mut out, "    }}").unwrap();
+    }
+    writeln!(&mut out, "}}").unwrap();
+
+    out
+}
+
+// ("IRQ", "GLOBAL") => IRQ, ("DMA", "rx") => DMA_RX
+fn signal_const_name(prefix: &str, signal: &str) -> String {
+    if signal == "GLOBAL" {
+        return prefix.to_string();
+    }
+    let signal: String = signal
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_{}", prefix, signal)
+}
+
+// TXD => TxdPin, CMP0_INN1 => Cmp0Inn1Pin
+fn signal_trait_name(signal: &str) -> String {
+    let mut name = String::new();
+    for part in signal.split('_') {
+        let mut chars = part.chars();
+        if let Some(c) = chars.next() {
+            name.push(c.to_ascii_uppercase());
+            name.push_str(&chars.as_str().to_ascii_lowercase());
+        }
+    }
+    name.push_str("Pin");
+    name
+}
+
+/// Drop pads, iomux settings and peripheral pins that the package doesn't bond out.
+fn filter_core_for_package(core: &Core, package: &Package) -> Core {
+    let pads: HashSet<&str> = package.pinout.iter().map(|p| p.pad.as_str()).collect();
+    // IOC_PA25_FUNC_CTL_PWM1_P_1, PIOC_PY00_FUNC_CTL_..., BIOC_PZ00_FUNC_CTL_...
+    let iomux_regex = Regex::new("^[PB]?IOC_(P[A-Z][0-9]+)_FUNC_CTL_").unwrap();
+
+    let mut core = core.clone();
+    core.pins.retain(|p| pads.contains(p.name.as_str()));
+    core.iomuxes
+        .retain(|mux| match iomux_regex.captures(&mux.name) {
+            Some(cap) => pads.contains(&cap[1]),
+            None => true,
+        });
+    for p in &mut core.peripherals {
+        p.pins.retain(|pin| pads.contains(pin.pin.as_str()));
+    }
+
+    core
+}
+
 fn gen_opts() -> generate::Options {
     generate::Options {
         common_module: CommonModule::External(TokenStream::from_str("crate::common").unwrap()),
     }
 }
 
-fn gen_memory_x(out_dir: &Path, chip: &Chip) {
-    let mut memory_x = String::new();
+/// Linker fragment placing the `boot_header` statics, for images executing in place from XPI0.
+fn gen_boot_header_x(boot: &Boot) -> String {
+    format!(
+        r#"/* XPI0 NOR boot layout, add with `-Tboot_header.x` */
+SECTIONS
+{{
+    .nor_cfg_option ORIGIN(XPI0) + 0x{:x} :
+    {{
+        KEEP(*(.nor_cfg_option))
+    }} > XPI0
+
+    .boot_header ORIGIN(XPI0) + 0x{:x} :
+    {{
+        __boot_header_start__ = .;
+        KEEP(*(.boot_header))
+        KEEP(*(.fw_info_table))
+    }} > XPI0
+}}
+INSERT BEFORE .text;
+
+/* the application follows the boot header, `_stext` is set by memory.x as riscv-rt needs it early */
+ASSERT(_stext == ORIGIN(XPI0) + 0x{app:x}, "boot_header.x: set `_stext = ORIGIN(XPI0) + 0x{app:x};` in memory.x");
+
+/* firmware info table symbols, as in the SDK's linker scripts, see `xip_fw_info_table!` */
+__app_load_addr__ = _stext;
+__app_offset__ = 0x{app:x} - 0x{header:x};
+__app_entry_point__ = _start;
+/* the image ends after the load data of riscv-rt's .data, or of .fast of `memory.x` */
+__fw_end__ = _sidata + (_edata - _sdata);
+__fw_end__ = DEFINED(__fast_load_addr__) ? MAX(__fw_end__, __fast_load_addr__ + (__fast_end__ - __fast_start__)) : __fw_end__;
+__fw_size__ = __fw_end__ - _stext;
+"#,
+        boot.nor_cfg_option_offset,
+        boot.boot_header_offset,
+        app = boot.app_offset,
+        header = boot.boot_header_offset,
+    )
+}
+
+/// Linker layouts, mirroring the SDK's `flash_xip.ld`, `ram.ld` and `flash_sdram_xip.ld`.
+/// Each is written to `memory_x/<flavour>/memory.x` and selected by a `memory-x*` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemoryLayout {
+    FlashXip,
+    Ram,
+    FlashSdramXip,
+}
+
+impl MemoryLayout {
+    fn dir_name(&self) -> &'static str {
+        match self {
+            MemoryLayout::FlashXip => "flash_xip",
+            MemoryLayout::Ram => "ram",
+            MemoryLayout::FlashSdramXip => "flash_sdram_xip",
+        }
+    }
+
+    // (text and rodata, data, bss and heap, stack)
+    fn regions(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            MemoryLayout::FlashXip => ("XPI0", "DLM", "DLM"),
+            MemoryLayout::Ram => ("ILM", "DLM", "DLM"),
+            MemoryLayout::FlashSdramXip => ("XPI0", "SDRAM", "DLM"),
+        }
+    }
+}
+
+// SDRAM on FEMC, the size depends on the board, same default as the SDK
+const FEMC_SDRAM_BASE: u32 = 0x4000_0000;
+const FEMC_SDRAM_DEFAULT_SIZE: &str = "32M";
 
+fn gen_memory_x(out_dir: &Path, memory: &[MemoryRegion], has_femc: bool, boot: Option<&Boot>) {
     // names:
     // AHB_SRAM
     // APB_SRAM
-    // AXI_SRAM
-    // AXI_SRAM_NONCACHEABLE
+    // AXI_SRAM, AXI_SRAM0, AXI_SRAM1
+    // AXI_SRAM_NOCACHE
     // DLM
     // ILM
-    // NONCACHEABLE_RAM
     // SDRAM
-    // SDRAM_NONCACHEABLE
     // SHARE_RAM
     // XPI0
 
-    let xpi0 = chip
-        .memory
+    let mut layouts = vec![MemoryLayout::FlashXip, MemoryLayout::Ram];
+    if has_femc || memory.iter().any(|r| r.name == "SDRAM") {
+        layouts.push(MemoryLayout::FlashSdramXip);
+    }
+
+    for layout in layouts {
+        let memory_x = gen_memory_x_for_layout(memory, layout, boot);
+        let dir = out_dir.join("memory_x").join(layout.dir_name());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("memory.x"), memory_x).unwrap();
+    }
+}
+
+fn gen_memory_x_for_layout(
+    memory: &[MemoryRegion],
+    layout: MemoryLayout,
+    boot: Option<&Boot>,
+) -> String {
+    let mut memory_x = String::new();
+
+    let find = |name: &str| memory.iter().find(|r| r.name == name);
+    // ILM/DLM are ILM0/DLM0 on chips without per-core local memory, like HPM6E50
+    let local = |name: &'static str| {
+        memory
+            .iter()
+            .find(|r| r.name.trim_end_matches(|c: char| c.is_ascii_digit()) == name)
+            .map_or(name, |r| r.name.as_str())
+    };
+    let (flash_address, flash_size) = memory
         .iter()
-        .filter(|r| r.kind == MemoryRegionKind::Flash && r.name.starts_with("XPI"));
-    let (flash_address, flash_size) = xpi0
-        .clone()
+        .filter(|r| r.kind == MemoryRegionKind::Flash && r.name.starts_with("XPI"))
         .map(|r| (r.address, r.size))
         .reduce(|acc, el| (u32::min(acc.0, el.0), acc.1 + el.1))
+        .expect("Cannot find FLASH(XPIx) region");
+    let has_sdram = find("SDRAM").is_some();
+
+    if layout == MemoryLayout::FlashSdramXip && !has_sdram {
+        writeln!(
+            memory_x,
+            "SDRAM_SIZE = DEFINED(_sdram_size) ? _sdram_size : {};\n",
+            FEMC_SDRAM_DEFAULT_SIZE
+        )
         .unwrap();
-    let dlm = chip
-        .memory
-        .iter()
-        .find(|r| r.kind == MemoryRegionKind::Ram && (r.name == "DLM" || r.name == "DLM0"))
-        .unwrap();
-    let ilm = chip
-        .memory
-        .iter()
-        .find(|r| r.kind == MemoryRegionKind::Ram && (r.name == "ILM" || r.name == "ILM0"))
-        .unwrap();
-    let axi_sram = chip
-        .memory
-        .iter()
-        .find(|r| r.kind == MemoryRegionKind::Ram && r.name == "AXI_SRAM");
-    let ahb_sram = chip
-        .memory
-        .iter()
-        .find(|r| r.kind == MemoryRegionKind::Ram && r.name == "AHB_SRAM");
-    let sdram = chip
-        .memory
-        .iter()
-        .find(|r| r.kind == MemoryRegionKind::Ram && r.name == "SDRAM");
+    }
 
-    write!(memory_x, "MEMORY\n{{\n").unwrap();
+    writeln!(memory_x, "MEMORY\n{{").unwrap();
     writeln!(
         memory_x,
-        "    XPI0  : ORIGIN = 0x{:08x}, LENGTH = {:>4}K /* bootheader and firmware */",
+        "    XPI0 : ORIGIN = 0x{:08x}, LENGTH = {}K /* bootheader and firmware */",
         flash_address,
         flash_size / 1024,
     )
     .unwrap();
-    writeln!(
-        memory_x,
-        "    DLM   : ORIGIN = 0x{:08x}, LENGTH = {:>4}K /* data local memory */",
-        dlm.address,
-        dlm.size / 1024,
-    )
-    .unwrap();
-    writeln!(
-        memory_x,
-        "    ILM   : ORIGIN = 0x{:08x}, LENGTH = {:>4}K /* instruction local memory */",
-        ilm.address,
-        ilm.size / 1024,
-    )
-    .unwrap();
-    if let Some(axi_sram) = axi_sram {
+    for region in memory
+        .iter()
+        .filter(|r| !(r.kind == MemoryRegionKind::Flash && r.name.starts_with("XPI")))
+    {
+        let comment = match region.name.trim_end_matches(|c: char| c.is_ascii_digit()) {
+            "ILM" => " /* instruction local memory */",
+            "DLM" => " /* data local memory */",
+            _ => "",
+        };
         writeln!(
             memory_x,
-            "    AXI_SRAM   : ORIGIN = 0x{:08x}, LENGTH = {:>4}K",
-            axi_sram.address,
-            axi_sram.size / 1024,
+            "    {} : ORIGIN = 0x{:08x}, LENGTH = {}K{}",
+            region.name,
+            region.address,
+            region.size / 1024,
+            comment,
         )
         .unwrap();
     }
-    if let Some(ahb_sram) = ahb_sram {
+    if layout == MemoryLayout::FlashSdramXip && !has_sdram {
         writeln!(
             memory_x,
-            "    AHB_SRAM   : ORIGIN = 0x{:08x}, LENGTH = {:>4}K",
-            ahb_sram.address,
-            ahb_sram.size / 1024,
+            "    SDRAM : ORIGIN = 0x{:08x}, LENGTH = SDRAM_SIZE",
+            FEMC_SDRAM_BASE
         )
         .unwrap();
     }
-    if let Some(sdram) = sdram {
+    writeln!(memory_x, "}}\n").unwrap();
+
+    let (text, data, stack) = layout.regions();
+    let (text, data, stack) = (local(text), local(data), local(stack));
+    writeln!(memory_x, "REGION_ALIAS(\"REGION_TEXT\", {});", text).unwrap();
+    writeln!(memory_x, "REGION_ALIAS(\"REGION_RODATA\", {});", text).unwrap();
+    writeln!(memory_x, "REGION_ALIAS(\"REGION_DATA\", {});", data).unwrap();
+    writeln!(memory_x, "REGION_ALIAS(\"REGION_BSS\", {});", data).unwrap();
+    writeln!(memory_x, "REGION_ALIAS(\"REGION_HEAP\", {});", data).unwrap();
+    writeln!(memory_x, "REGION_ALIAS(\"REGION_STACK\", {});", stack).unwrap();
+
+    // riscv-rt places .text at `_stext`, it must be set before its SECTIONS, not in boot_header.x
+    if let (Some(boot), "XPI0") = (boot, text) {
         writeln!(
             memory_x,
-            "    SDRAM   : ORIGIN = 0x{:08x}, LENGTH = {:>4}K",
-            sdram.address,
-            sdram.size / 1024,
+            "\n/* the application follows the boot header, see boot_header.x */\n_stext = ORIGIN(XPI0) + 0x{:x};",
+            boot.app_offset
         )
         .unwrap();
     }
-    write!(memory_x, "}}").unwrap();
 
+    // Local memories are never cached, use DLM when there is no dedicated noncacheable RAM
+    let noncacheable = find("AXI_SRAM_NOCACHE").map_or(local("DLM"), |r| r.name.as_str());
+    let ilm = local("ILM");
+
+    writeln!(memory_x, "\nSECTIONS\n{{").unwrap();
+    // riscv-rt does not copy code to ILM, `hpm_metapac::copy_fast_section` does, load address in REGION_RODATA
     write!(
         memory_x,
-        r#"
-REGION_ALIAS("REGION_TEXT", XPI0);
-REGION_ALIAS("REGION_RODATA", XPI0);
-REGION_ALIAS("REGION_DATA", DLM);
-REGION_ALIAS("REGION_BSS", DLM);
-REGION_ALIAS("REGION_HEAP", DLM);
-REGION_ALIAS("REGION_STACK", DLM);
-    "#
+        r#"    .fast : ALIGN(8)
+    {{
+        __fast_load_addr__ = LOADADDR(.fast);
+        __fast_start__ = .;
+        *(.fast .fast.*)
+        . = ALIGN(8);
+        __fast_end__ = .;
+    }} > {ilm} AT > REGION_RODATA
+
+    .noncacheable (NOLOAD) : ALIGN(8)
+    {{
+        __noncacheable_start__ = .;
+        *(.noncacheable .noncacheable.*)
+        . = ALIGN(8);
+        __noncacheable_end__ = .;
+    }} > {noncacheable}
+"#
     )
     .unwrap();
+    if find("AHB_SRAM").is_some() {
+        write!(
+            memory_x,
+            r#"
+    .ahb_sram (NOLOAD) : ALIGN(8)
+    {{
+        __ahb_sram_start__ = .;
+        *(.ahb_sram .ahb_sram.*)
+        . = ALIGN(8);
+        __ahb_sram_end__ = .;
+    }} > AHB_SRAM
+"#
+        )
+        .unwrap();
+    }
+    if find("SHARE_RAM").is_some() {
+        write!(
+            memory_x,
+            r#"
+    .sh_mem (NOLOAD) : ALIGN(8)
+    {{
+        __share_mem_start__ = .;
+        *(.sh_mem .sh_mem.*)
+        . = ALIGN(8);
+        __share_mem_end__ = .;
+    }} > SHARE_RAM
+"#
+        )
+        .unwrap();
+    }
+    if layout == MemoryLayout::FlashSdramXip {
+        write!(
+            memory_x,
+            r#"
+    .sdram (NOLOAD) : ALIGN(8)
+    {{
+        __sdram_start__ = .;
+        *(.sdram .sdram.*)
+        . = ALIGN(8);
+        __sdram_end__ = .;
+    }} > SDRAM
+"#
+        )
+        .unwrap();
+    }
+    writeln!(memory_x, "}}\nINSERT AFTER .bss;").unwrap();
+
+    memory_x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // UART OSCR, IIR and LSR of data/registers/uart_v53.yaml, as hpm-data-gen writes them
+    fn uart_json() -> serde_json::Value {
+        serde_json::json!({
+            "block/UART": {
+                "items": [
+                    {"name": "OSCR", "byte_offset": 20, "fieldset": "OSCR", "reset_value": 16},
+                    {"name": "IIR", "byte_offset": 40, "fieldset": "IIR", "reset_value": 1},
+                    {"name": "LSR", "byte_offset": 52, "fieldset": "LSR"},
+                ],
+            },
+            "fieldset/OSCR": {
+                "fields": [{"name": "OSC", "bit_offset": 0, "bit_size": 5}],
+            },
+            "fieldset/IIR": {
+                "fields": [
+                    {"name": "INTRID", "bit_offset": 0, "bit_size": 4},
+                    {"name": "FIFOED", "bit_offset": 6, "bit_size": 2},
+                    {"name": "RXIDLE_FLAG", "bit_offset": 31, "bit_size": 1, "modified_write_values": "oneToClear"},
+                ],
+            },
+            "fieldset/LSR": {
+                "fields": [
+                    {"name": "DR", "bit_offset": 0, "bit_size": 1},
+                    {"name": "PE", "bit_offset": 2, "bit_size": 1, "read_action": "clear"},
+                ],
+            },
+        })
+    }
+
+    #[test]
+    fn reset_values() {
+        let (chiptool_ir, ir) = registers_ir(uart_json());
+
+        let reset_values: BTreeMap<_, _> = ir.blocks[0]
+            .items
+            .iter()
+            .map(|item| match &item.inner {
+                data::ir::BlockItemInner::Register(r) => (item.name.as_str(), r.reset_value),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            reset_values,
+            [("iir", Some(1)), ("lsr", None), ("oscr", Some(16))].into()
+        );
+
+        let iir = ir.fieldsets.iter().find(|f| f.name == "Iir").unwrap();
+        assert_eq!(
+            iir.fields[2].modified_write_values,
+            Some(data::ir::ModifiedWriteValues::OneToClear)
+        );
+        let lsr = ir.fieldsets.iter().find(|f| f.name == "Lsr").unwrap();
+        assert_eq!(lsr.fields[1].read_action, Some(data::ir::ReadAction::Clear));
+
+        let items = generate::render(&chiptool_ir, &gen_opts()).unwrap();
+        let items = fieldset_impls(items, &ir).to_string();
+        assert!(items.contains("fn default () -> Oscr { Oscr (0x10) }"));
+        assert!(items.contains("fn default () -> Iir { Iir (0x1) }"));
+        assert!(items.contains("fn default () -> Lsr { Lsr (0) }"));
+    }
+
+    // what fieldset_impls gives IIR
+    #[derive(Copy, Clone, Default)]
+    struct Iir(u32);
+    impl common::WriteOneToClear for Iir {
+        fn without_w1c(self) -> Iir {
+            Iir(self.0 & !0x80000000)
+        }
+    }
+
+    #[test]
+    fn w1c() {
+        let (chiptool_ir, ir) = registers_ir(uart_json());
+        let items = generate::render(&chiptool_ir, &gen_opts()).unwrap();
+        let items = fieldset_impls(items, &ir).to_string();
+        assert!(items.contains(
+            "impl crate :: common :: WriteOneToClear for Iir { # [inline (always)] \
+             fn without_w1c (self) -> Iir { Iir (self . 0 & ! 0x80000000) } }"
+        ));
+        assert!(items.contains("impl crate :: common :: WriteOneToClear for Lsr { }"));
+
+        // RXIDLE_FLAG and INTRID 1 read, setting FIFOED writes back INTRID but a zero
+        // RXIDLE_FLAG, writing its one back would clear it
+        let mut iir = 0x8000_0001u32;
+        let reg: common::Reg<Iir, common::RW> =
+            unsafe { common::Reg::from_ptr(&mut iir as *mut u32 as *mut Iir) };
+        reg.modify(|w| w.0 |= 0b11 << 6);
+        assert_eq!(iir, 0x0000_00c1);
+    }
 
-    fs::create_dir_all(out_dir.join("memory_x")).unwrap();
-    let mut file = File::create(out_dir.join("memory_x").join("memory.x")).unwrap();
-    file.write_all(memory_x.as_bytes()).unwrap();
+    // made-up core, PA01 and PY00 not bonded out
+    #[test]
+    fn package_filter() {
+        let core: Core = serde_json::from_value(serde_json::json!({
+            "name": "RV32",
+            "peripherals": [{
+                "name": "UART0",
+                "address": 0xf0040000u32,
+                "pins": [
+                    {"pin": "PA00", "signal": "TXD", "alt": 2},
+                    {"pin": "PA01", "signal": "RXD", "alt": 2},
+                ],
+            }],
+            "dma_channels": [],
+            "pins": [
+                {"name": "PA00", "index": 0},
+                {"name": "PA01", "index": 1},
+                {"name": "PY00", "index": 448},
+            ],
+            "iomuxes": [
+                {"name": "IOC_PA00_FUNC_CTL_UART0_TXD", "value": 2},
+                {"name": "IOC_PA01_FUNC_CTL_UART0_RXD", "value": 2},
+                {"name": "PIOC_PY00_FUNC_CTL_SOC_GPIO_Y_00", "value": 3},
+                {"name": "IOC_PAD_FUNC_CTL_ALT_SELECT_MASK", "value": 31},
+            ],
+        }))
+        .unwrap();
+        let package: Package = serde_json::from_value(serde_json::json!({
+            "name": "HPM5361xCFx",
+            "package": "LQFP64",
+            "pins": 64,
+            "pinout": [{"pin": "1", "pad": "PA00"}],
+        }))
+        .unwrap();
+
+        let core = filter_core_for_package(&core, &package);
+        let pins: Vec<_> = core.pins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(pins, ["PA00"]);
+        let iomuxes: Vec<_> = core.iomuxes.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            iomuxes,
+            [
+                "IOC_PA00_FUNC_CTL_UART0_TXD",
+                "IOC_PAD_FUNC_CTL_ALT_SELECT_MASK"
+            ]
+        );
+        let uart_pins: Vec<_> = core.peripherals[0]
+            .pins
+            .iter()
+            .map(|p| p.signal.as_str())
+            .collect();
+        assert_eq!(uart_pins, ["TXD"]);
+    }
 }